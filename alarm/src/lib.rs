@@ -0,0 +1,479 @@
+/* Alarm clock logic for the clock examples.
+ *
+ * An alarm rings at a local time (hour and minute) on the days in its weekday mask, or once
+ * if the mask is empty (then it disables itself when dismissed). `Scheduler` holds the alarms
+ * and is polled from the main loop with a `Clock`; it tells when one starts ringing and keeps
+ * track of snoozes. Nothing here touches the hardware, the tests run it on a fake clock.
+ *
+ * Times are local seconds since 1970-01-01 00:00 (the Unix timestamp plus the zone offset). */
+
+#![no_std]
+
+pub const MAX_LABEL: usize = 16;
+
+/* Size of an encoded alarm, see `Alarm::encode` */
+pub const ENCODED_LEN: usize = 5 + MAX_LABEL;
+
+/* Minutes looked at again after the main loop stalled (or the alarm view was up), longer
+ * jumps (e.g. the first time sync) only check the current minute */
+const MAX_CATCH_UP_MINUTES: i64 = 15;
+
+const MINUTES_PER_DAY: i64 = 24 * 60;
+
+/* Local time source, the hardware clock on the device and a settable one in tests */
+pub trait Clock {
+    fn now(&self) -> i64;
+}
+
+/* Bit 0 is Monday, bit 6 Sunday */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Weekdays(pub u8);
+
+impl Weekdays {
+    pub const NONE: Weekdays = Weekdays(0);
+    pub const WORKDAYS: Weekdays = Weekdays(0b001_1111);
+    pub const WEEKEND: Weekdays = Weekdays(0b110_0000);
+    pub const EVERY_DAY: Weekdays = Weekdays(0b111_1111);
+
+    /* `weekday` 0 is Monday */
+    pub const fn contains(self, weekday: u8) -> bool {
+        weekday < 7 && self.0 & (1 << weekday) != 0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Alarm {
+    pub hour: u8,
+    pub minute: u8,
+    pub weekdays: Weekdays,
+    pub enabled: bool,
+    label: [u8; MAX_LABEL],
+    label_len: u8,
+}
+
+impl Alarm {
+    /* Enabled, `label` is cut to `MAX_LABEL` bytes */
+    pub fn new(hour: u8, minute: u8, weekdays: Weekdays, label: &str) -> Self {
+        assert!(hour < 24 && minute < 60, "alarm time out of range");
+        let mut len = label.len().min(MAX_LABEL);
+        while !label.is_char_boundary(len) {
+            len -= 1;
+        }
+        let mut alarm = Alarm { hour, minute, weekdays, enabled: true, label: [0; MAX_LABEL], label_len: len as u8 };
+        alarm.label[..len].copy_from_slice(&label.as_bytes()[..len]);
+        alarm
+    }
+
+    pub fn label(&self) -> &str {
+        core::str::from_utf8(&self.label[..self.label_len as usize]).unwrap_or("")
+    }
+
+    pub fn is_one_shot(&self) -> bool {
+        self.weekdays == Weekdays::NONE
+    }
+
+    /* `minute` in local minutes since 1970-01-01 00:00 */
+    fn rings_at(&self, minute: i64) -> bool {
+        let minute_of_day = minute.rem_euclid(MINUTES_PER_DAY);
+        /* 1970-01-01 was a Thursday */
+        let weekday = (minute.div_euclid(MINUTES_PER_DAY) + 3).rem_euclid(7) as u8;
+
+        self.enabled
+            && minute_of_day == self.hour as i64 * 60 + self.minute as i64
+            && (self.is_one_shot() || self.weekdays.contains(weekday))
+    }
+
+    /* hour, minute, weekdays, enabled, label length, label (zero padded) */
+    pub fn encode(&self) -> [u8; ENCODED_LEN] {
+        let mut bytes = [0; ENCODED_LEN];
+        bytes[..5].copy_from_slice(&[self.hour, self.minute, self.weekdays.0, self.enabled as u8, self.label_len]);
+        bytes[5..].copy_from_slice(&self.label);
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let bytes: &[u8; ENCODED_LEN] = bytes.try_into().ok()?;
+        let [hour, minute, weekdays, enabled, label_len] = [bytes[0], bytes[1], bytes[2], bytes[3], bytes[4]];
+        if hour >= 24 || minute >= 60 || weekdays > Weekdays::EVERY_DAY.0 || label_len as usize > MAX_LABEL {
+            return None;
+        }
+        let label = &bytes[5..5 + label_len as usize];
+        core::str::from_utf8(label).ok()?;
+
+        let mut alarm = Alarm::new(hour, minute, Weekdays(weekdays), "");
+        alarm.enabled = enabled != 0;
+        alarm.label[..label.len()].copy_from_slice(label);
+        alarm.label_len = label_len;
+        Some(alarm)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Ringing {
+    pub alarm: usize,
+    pub since: i64,
+}
+
+pub struct Scheduler<const N: usize> {
+    alarms: [Option<Alarm>; N],
+    snooze_secs: i64,
+    /* Ringing this long without anyone around counts as dismissed */
+    ring_limit_secs: i64,
+    /* Last minute checked for due alarms, so each one is only looked at once */
+    last_minute: Option<i64>,
+    /* Alarms that already rang in the minute after `last_minute`, several can be set for it */
+    rung: [bool; N],
+    ringing: Option<Ringing>,
+    /* When snoozed alarms ring again */
+    snoozed: [Option<i64>; N],
+}
+
+impl<const N: usize> Scheduler<N> {
+    pub const fn new(snooze_secs: i64, ring_limit_secs: i64) -> Self {
+        Scheduler {
+            alarms: [None; N],
+            snooze_secs,
+            ring_limit_secs,
+            last_minute: None,
+            rung: [false; N],
+            ringing: None,
+            snoozed: [None; N],
+        }
+    }
+
+    /* Index of the new alarm, `None` when all `N` places are taken */
+    pub fn add(&mut self, alarm: Alarm) -> Option<usize> {
+        let index = self.alarms.iter().position(Option::is_none)?;
+        self.alarms[index] = Some(alarm);
+        Some(index)
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if self.ringing.map(|ringing| ringing.alarm) == Some(index) {
+            self.ringing = None;
+        }
+        self.alarms[index] = None;
+        self.rung[index] = false;
+        self.snoozed[index] = None;
+    }
+
+    pub fn alarm(&self, index: usize) -> Option<&Alarm> {
+        self.alarms.get(index)?.as_ref()
+    }
+
+    pub fn alarms(&self) -> impl Iterator<Item = (usize, &Alarm)> {
+        self.alarms.iter().enumerate().filter_map(|(index, alarm)| Some((index, alarm.as_ref()?)))
+    }
+
+    pub fn ringing(&self) -> Option<Ringing> {
+        self.ringing
+    }
+
+    pub fn is_snoozed(&self) -> bool {
+        self.snoozed.iter().any(Option::is_some)
+    }
+
+    /* Checks the alarms against `clock`, returns the one that started ringing. Only one rings
+     * at a time, others due meanwhile ring after it (if within `MAX_CATCH_UP_MINUTES`) */
+    pub fn poll(&mut self, clock: &impl Clock) -> Option<usize> {
+        let now = clock.now();
+        let minute = now.div_euclid(60);
+
+        if let Some(ringing) = self.ringing {
+            if now - ringing.since < self.ring_limit_secs {
+                return None;
+            }
+            self.dismiss();
+        }
+
+        let due = self.snoozed.iter().position(|until| until.is_some_and(|until| until <= now));
+        if let Some(index) = due {
+            self.snoozed[index] = None;
+            return self.ring(index, now);
+        }
+
+        let first = match self.last_minute {
+            Some(last) if last >= minute => {
+                /* The clock was set back (or corrected by a sync), a few minutes don't ring
+                 * twice, a longer step back starts over from the new time */
+                if last - minute > MAX_CATCH_UP_MINUTES {
+                    self.last_minute = Some(minute);
+                    self.rung = [false; N];
+                }
+                return None;
+            }
+            Some(last) if minute - last <= MAX_CATCH_UP_MINUTES => last + 1,
+            _ => {
+                self.rung = [false; N];
+                minute
+            }
+        };
+        for checked in first..=minute {
+            let due = self
+                .alarms()
+                .find(|(index, alarm)| !self.rung[*index] && alarm.rings_at(checked))
+                .map(|(index, _)| index);
+            if let Some(index) = due {
+                /* The minute isn't done yet, the next poll looks at it again */
+                self.last_minute = Some(checked - 1);
+                self.rung[index] = true;
+                return self.ring(index, now);
+            }
+            self.rung = [false; N];
+        }
+        self.last_minute = Some(minute);
+        None
+    }
+
+    /* Stops the ringing alarm, it rings again after the snooze time */
+    pub fn snooze(&mut self, clock: &impl Clock) {
+        if let Some(ringing) = self.ringing.take() {
+            self.snoozed[ringing.alarm] = Some(clock.now() + self.snooze_secs);
+        }
+    }
+
+    /* Stops the ringing alarm, or cancels the snoozes when none is ringing. Returns `true`
+     * if a one-shot alarm got disabled, which should be stored */
+    pub fn dismiss(&mut self) -> bool {
+        let Some(ringing) = self.ringing.take() else {
+            let mut disabled = false;
+            for index in 0..N {
+                if self.snoozed[index].take().is_some() {
+                    disabled |= self.disable_one_shot(index);
+                }
+            }
+            return disabled;
+        };
+        self.snoozed[ringing.alarm] = None;
+        self.disable_one_shot(ringing.alarm)
+    }
+
+    fn disable_one_shot(&mut self, index: usize) -> bool {
+        match &mut self.alarms[index] {
+            Some(alarm) if alarm.is_one_shot() && alarm.enabled => {
+                alarm.enabled = false;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn ring(&mut self, index: usize, now: i64) -> Option<usize> {
+        self.ringing = Some(Ringing { alarm: index, since: now });
+        Some(index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+
+    const SNOOZE: i64 = 5 * 60;
+    const RING_LIMIT: i64 = 10 * 60;
+
+    /* 2024-01-01 00:00 local, a Monday */
+    const MONDAY: i64 = 19723 * 24 * 3600;
+
+    struct FakeClock(Cell<i64>);
+
+    impl FakeClock {
+        fn at(day: i64, hour: i64, minute: i64) -> Self {
+            FakeClock(Cell::new(MONDAY + day * 24 * 3600 + hour * 3600 + minute * 60))
+        }
+
+        fn advance(&self, seconds: i64) {
+            self.0.set(self.0.get() + seconds);
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> i64 {
+            self.0.get()
+        }
+    }
+
+    fn scheduler(alarms: &[Alarm]) -> Scheduler<4> {
+        let mut scheduler = Scheduler::new(SNOOZE, RING_LIMIT);
+        for alarm in alarms {
+            scheduler.add(*alarm).unwrap();
+        }
+        scheduler
+    }
+
+    #[test]
+    fn rings_once_at_the_set_minute() {
+        let mut alarms = scheduler(&[Alarm::new(7, 30, Weekdays::EVERY_DAY, "Wake up")]);
+        let clock = FakeClock::at(0, 7, 29);
+
+        assert_eq!(alarms.poll(&clock), None);
+        clock.advance(60);
+        assert_eq!(alarms.poll(&clock), Some(0));
+        assert_eq!(alarms.ringing(), Some(Ringing { alarm: 0, since: clock.now() }));
+
+        alarms.dismiss();
+        clock.advance(30);
+        assert_eq!(alarms.poll(&clock), None);
+        assert_eq!(alarms.ringing(), None);
+    }
+
+    #[test]
+    fn follows_the_weekday_mask() {
+        let mut alarms = scheduler(&[Alarm::new(8, 0, Weekdays::WEEKEND, "Weekend")]);
+
+        /* Monday to Friday quiet, Saturday and Sunday ring */
+        for day in 0..7 {
+            let clock = FakeClock::at(day, 8, 0);
+            let rang = alarms.poll(&clock).is_some();
+            assert_eq!(rang, day >= 5, "day {}", day);
+            alarms.dismiss();
+        }
+    }
+
+    #[test]
+    fn snooze_rings_again_later() {
+        let mut alarms = scheduler(&[Alarm::new(6, 0, Weekdays::WORKDAYS, "Work")]);
+        let clock = FakeClock::at(0, 6, 0);
+        assert_eq!(alarms.poll(&clock), Some(0));
+
+        alarms.snooze(&clock);
+        assert!(alarms.ringing().is_none());
+        assert!(alarms.is_snoozed());
+
+        clock.advance(SNOOZE - 1);
+        assert_eq!(alarms.poll(&clock), None);
+        clock.advance(1);
+        assert_eq!(alarms.poll(&clock), Some(0));
+
+        /* Dismissing with a snooze pending cancels it */
+        alarms.snooze(&clock);
+        alarms.dismiss();
+        assert!(!alarms.is_snoozed());
+        clock.advance(SNOOZE);
+        assert_eq!(alarms.poll(&clock), None);
+    }
+
+    #[test]
+    fn one_shot_disables_itself() {
+        let mut alarms = scheduler(&[Alarm::new(12, 0, Weekdays::NONE, "Lunch")]);
+        let clock = FakeClock::at(2, 12, 0);
+        assert_eq!(alarms.poll(&clock), Some(0));
+        assert!(alarms.dismiss());
+        assert!(!alarms.alarm(0).unwrap().enabled);
+
+        clock.advance(24 * 3600);
+        assert_eq!(alarms.poll(&clock), None);
+    }
+
+    #[test]
+    fn cancelled_snooze_disables_one_shot() {
+        let mut alarms = scheduler(&[Alarm::new(12, 0, Weekdays::NONE, "Lunch")]);
+        let clock = FakeClock::at(2, 12, 0);
+        assert_eq!(alarms.poll(&clock), Some(0));
+        alarms.snooze(&clock);
+
+        /* Dismissed while snoozed, not ringing */
+        assert!(alarms.dismiss());
+        assert!(!alarms.alarm(0).unwrap().enabled);
+        assert!(!alarms.dismiss());
+
+        clock.advance(24 * 3600);
+        assert_eq!(alarms.poll(&clock), None);
+    }
+
+    #[test]
+    fn same_minute_alarms_ring_one_after_the_other() {
+        let mut alarms = scheduler(&[
+            Alarm::new(7, 0, Weekdays::EVERY_DAY, "a"),
+            Alarm::new(7, 0, Weekdays::EVERY_DAY, "b"),
+        ]);
+        let clock = FakeClock::at(0, 7, 0);
+        assert_eq!(alarms.poll(&clock), Some(0));
+        alarms.dismiss();
+        assert_eq!(alarms.poll(&clock), Some(1));
+        alarms.dismiss();
+
+        clock.advance(30);
+        assert_eq!(alarms.poll(&clock), None);
+        clock.advance(60);
+        assert_eq!(alarms.poll(&clock), None);
+
+        /* Both again the next day */
+        clock.advance(24 * 3600 - 90);
+        assert_eq!(alarms.poll(&clock), Some(0));
+        alarms.dismiss();
+        assert_eq!(alarms.poll(&clock), Some(1));
+    }
+
+    #[test]
+    fn stops_ringing_after_the_limit() {
+        let mut alarms = scheduler(&[Alarm::new(7, 0, Weekdays::EVERY_DAY, "")]);
+        let clock = FakeClock::at(0, 7, 0);
+        assert_eq!(alarms.poll(&clock), Some(0));
+        clock.advance(RING_LIMIT);
+        assert_eq!(alarms.poll(&clock), None);
+        assert_eq!(alarms.ringing(), None);
+    }
+
+    #[test]
+    fn catches_up_after_a_stall_but_not_after_a_jump() {
+        let mut alarms = scheduler(&[
+            Alarm::new(7, 0, Weekdays::EVERY_DAY, "First"),
+            Alarm::new(7, 2, Weekdays::EVERY_DAY, "Second"),
+        ]);
+        let clock = FakeClock::at(0, 6, 59);
+        assert_eq!(alarms.poll(&clock), None);
+
+        /* Main loop busy for 5 minutes, both still ring, one after the other */
+        clock.advance(5 * 60);
+        assert_eq!(alarms.poll(&clock), Some(0));
+        alarms.dismiss();
+        assert_eq!(alarms.poll(&clock), Some(1));
+        alarms.dismiss();
+        assert_eq!(alarms.poll(&clock), None);
+
+        /* The first time sync jumps from 1970, nothing from the years in between rings */
+        let mut alarms = scheduler(&[Alarm::new(7, 0, Weekdays::EVERY_DAY, "")]);
+        let clock = FakeClock(Cell::new(0));
+        assert_eq!(alarms.poll(&clock), None);
+        clock.0.set(FakeClock::at(0, 7, 1).now());
+        assert_eq!(alarms.poll(&clock), None);
+    }
+
+    #[test]
+    fn clock_set_back_does_not_ring_twice() {
+        let mut alarms = scheduler(&[Alarm::new(7, 0, Weekdays::EVERY_DAY, "")]);
+        let clock = FakeClock::at(0, 7, 0);
+        assert_eq!(alarms.poll(&clock), Some(0));
+        alarms.dismiss();
+
+        clock.advance(-30);
+        assert_eq!(alarms.poll(&clock), None);
+        clock.advance(30);
+        assert_eq!(alarms.poll(&clock), None);
+    }
+
+    #[test]
+    fn encodes_and_decodes() {
+        let mut alarm = Alarm::new(23, 59, Weekdays(0b101_0101), "Überlanges Etikett");
+        alarm.enabled = false;
+        /* Cut on a character boundary */
+        assert_eq!(alarm.label(), "Überlanges Etik");
+
+        assert_eq!(Alarm::decode(&alarm.encode()), Some(alarm));
+        assert_eq!(Alarm::decode(&[0; 3]), None);
+
+        let mut bad = alarm.encode();
+        bad[0] = 24;
+        assert_eq!(Alarm::decode(&bad), None);
+    }
+
+    #[test]
+    fn full_scheduler_and_removal() {
+        let mut alarms = scheduler(&[Alarm::new(1, 0, Weekdays::NONE, ""); 4]);
+        assert_eq!(alarms.add(Alarm::new(2, 0, Weekdays::NONE, "")), None);
+        alarms.remove(2);
+        assert_eq!(alarms.alarms().count(), 3);
+        assert_eq!(alarms.add(Alarm::new(2, 0, Weekdays::NONE, "")), Some(2));
+    }
+}
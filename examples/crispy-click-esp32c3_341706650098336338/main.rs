@@ -3,10 +3,13 @@
 
 use esp32c3_hal::{
     clock::ClockControl,
-    peripherals::Peripherals,
+    interrupt,
+    peripherals::{self, Peripherals},
     gpio::*,
     prelude::*,
+    riscv,
     spi,
+    systimer::SystemTimer,
     timer::TimerGroup,
     Rtc,
     IO,
@@ -14,6 +17,9 @@ use esp32c3_hal::{
 };
 
 
+use core::cell::RefCell;
+use critical_section::Mutex;
+
 use mipidsi::{ Orientation, ColorOrder };
 
 use display_interface_spi::SPIInterfaceNoCS;
@@ -38,56 +44,58 @@ const TEXT_STYLE : TextStyle = TextStyleBuilder::new()
     .build();
 
 
-/* Debouncing algorythm */
-pub enum Event {
-    Pressed,
-    Released,
-    Nothing,
-}
-pub struct Button<T> {
-    button: T,
-    pressed: bool,
+use input::{ButtonState, Event, GestureConfig, DEFAULT_DEBOUNCE_MS};
+use input::queue::{Edge, EdgeQueue};
+
+/* Indexes of the buttons in `Edge::button` and in the array of button states */
+const GREEN: usize = 0;
+const BLUE: usize = 1;
+
+/* Buttons are owned by the GPIO interrupt, which only records edges, the main loop drains them */
+static BUTTON_PINS: Mutex<RefCell<Option<(Gpio0<Input<PullUp>>, Gpio1<Input<PullUp>>)>>> =
+    Mutex::new(RefCell::new(None));
+static EDGES: EdgeQueue<32> = EdgeQueue::new();
+
+/* Monotonic time in milliseconds, used to timestamp button edges */
+fn millis() -> u64 {
+    SystemTimer::now() / (SystemTimer::TICKS_PER_SECOND / 1000)
 }
-impl<T: ::embedded_hal::digital::v2::InputPin<Error = core::convert::Infallible>> Button<T> {
-    pub fn new(button: T) -> Self {
-        Button {
-            button,
-            pressed: true,
-        }
-    }
-    pub fn check(&mut self){
-        self.pressed = !self.button.is_low().unwrap();
-    }
 
-    pub fn poll(&mut self, delay :&mut Delay) -> Event {
-        let pressed_now = !self.button.is_low().unwrap();
-        if !self.pressed  &&  pressed_now
-        {
-            delay.delay_ms(30 as u32);
-            self.check();
-            if !self.button.is_low().unwrap() {
-                Event::Pressed
+#[interrupt]
+fn GPIO() {
+    let now = millis();
+    critical_section::with(|cs| {
+        if let Some((green, blue)) = BUTTON_PINS.borrow_ref_mut(cs).as_mut() {
+            if green.is_interrupt_set() {
+                EDGES.push(Edge { button: GREEN as u8, pressed: green.is_low().unwrap(), timestamp: now });
+                green.clear_interrupt();
             }
-            else {
-                Event::Nothing
+            if blue.is_interrupt_set() {
+                EDGES.push(Edge { button: BLUE as u8, pressed: blue.is_low().unwrap(), timestamp: now });
+                blue.clear_interrupt();
             }
         }
-        else if self.pressed && !pressed_now{
-            delay.delay_ms(30 as u32);
-            self.check();
-            if self.button.is_low().unwrap()
-            {
-                Event::Released
-            }
-            else {
-                Event::Nothing
-            }
+    });
+}
+
+/* Replays queued edges first (so presses made while drawing aren't lost), then advances timers */
+fn next_event(buttons: &mut [ButtonState; 2]) -> Option<(usize, Event)> {
+    while let Some(edge) = EDGES.pop() {
+        let button = edge.button as usize;
+        match buttons[button].update(edge.pressed, edge.timestamp) {
+            Event::Nothing => {}
+            event => return Some((button, event)),
         }
-        else{
-            Event::Nothing
+    }
+
+    let now = millis();
+    for (button, state) in buttons.iter_mut().enumerate() {
+        match state.tick(now) {
+            Event::Nothing => {}
+            event => return Some((button, event)),
         }
-        
     }
+    None
 }
 
 
@@ -167,8 +175,21 @@ fn main() -> ! {
 
     display.clear(Rgb565::WHITE).unwrap();
 
-    let mut button_green = Button::new(io.pins.gpio0.into_pull_up_input());
-    let mut button_blue  = Button::new(io.pins.gpio1.into_pull_up_input());
+    let mut button_green = io.pins.gpio0.into_pull_up_input();
+    let mut button_blue  = io.pins.gpio1.into_pull_up_input();
+
+    button_green.listen(esp32c3_hal::gpio::Event::AnyEdge);
+    button_blue.listen(esp32c3_hal::gpio::Event::AnyEdge);
+
+    critical_section::with(|cs| BUTTON_PINS.borrow_ref_mut(cs).replace((button_green, button_blue)));
+
+    interrupt::enable(peripherals::Interrupt::GPIO, interrupt::Priority::Priority2).unwrap();
+    unsafe { riscv::interrupt::enable(); }
+
+    let mut buttons = [
+        ButtonState::new(DEFAULT_DEBOUNCE_MS, GestureConfig::new()),
+        ButtonState::new(DEFAULT_DEBOUNCE_MS, GestureConfig::new()),
+    ];
 
  
     Text::with_text_style("Press GREEN button",
@@ -194,7 +215,9 @@ fn main() -> ! {
     let mut last_pressed_blue : bool = false;
 
     loop {
-        if let Event::Pressed = button_green.poll(&mut delay)
+        let input = next_event(&mut buttons);
+
+        if let Some((GREEN, Event::Pressed)) = input
         {
             green_cnt += 1;
 
@@ -241,7 +264,7 @@ fn main() -> ! {
             }
             last_pressed_blue = false;
         }
-        if let Event::Pressed = button_blue.poll(&mut delay)
+        if let Some((BLUE, Event::Pressed)) = input
         {
             blue_cnt += 1;
 
@@ -0,0 +1,111 @@
+/* Alarms of the clock.
+ *
+ * The scheduling is in the `alarm` crate (host tested), this part stores the alarms in NVS as
+ * one blob of encoded alarms, gives the scheduler the local time and drives the buzzer: a
+ * passive one on a LEDC channel, beeping on and off while an alarm rings. */
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::*;
+use log::*;
+
+use alarm::{Alarm, Clock, Scheduler, ENCODED_LEN};
+use esp_idf_hal::ledc::LedcDriver;
+use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs, NvsDefault};
+use tz::TimeZone;
+
+pub const MAX_ALARMS: usize = 8;
+
+/* 9 minutes like most alarm clocks, a ringing alarm nobody stops goes quiet after 10 */
+const SNOOZE_SECS: i64 = 9 * 60;
+const RING_LIMIT_SECS: i64 = 10 * 60;
+
+/* Length of the beeps and of the pauses between them */
+const BEEP_MS: u64 = 500;
+
+const NVS_NAMESPACE: &str = "alarms";
+const ALARMS_KEY: &str = "alarms";
+
+pub type Alarms = Scheduler<MAX_ALARMS>;
+
+/* Stored alarms, or `defaults` (stored right away) the first time */
+pub fn load(nvs: EspDefaultNvsPartition, defaults: &[Alarm]) -> Result<Alarms> {
+    let storage = EspNvs::<NvsDefault>::new(nvs.clone(), NVS_NAMESPACE, true)?;
+    let mut blob = [0; MAX_ALARMS * ENCODED_LEN];
+    let mut alarms = Scheduler::new(SNOOZE_SECS, RING_LIMIT_SECS);
+
+    match storage.get_raw(ALARMS_KEY, &mut blob)? {
+        Some(stored) => {
+            for encoded in stored.chunks(ENCODED_LEN) {
+                match Alarm::decode(encoded) {
+                    Some(alarm) => {
+                        alarms.add(alarm);
+                    }
+                    None => warn!("Skipping a damaged stored alarm"),
+                }
+            }
+        }
+        None => {
+            for alarm in defaults {
+                alarms.add(*alarm);
+            }
+            store(nvs, &alarms)?;
+        }
+    }
+
+    for (_, alarm) in alarms.alarms() {
+        info!(
+            "Alarm {:02}:{:02} {} (days {:07b}{})",
+            alarm.hour,
+            alarm.minute,
+            alarm.label(),
+            alarm.weekdays.0,
+            if alarm.enabled { "" } else { ", off" }
+        );
+    }
+    Ok(alarms)
+}
+
+pub fn store(nvs: EspDefaultNvsPartition, alarms: &Alarms) -> Result<()> {
+    let mut storage = EspNvs::<NvsDefault>::new(nvs, NVS_NAMESPACE, true)?;
+    let mut blob = Vec::with_capacity(MAX_ALARMS * ENCODED_LEN);
+    for (_, alarm) in alarms.alarms() {
+        blob.extend_from_slice(&alarm.encode());
+    }
+    storage.set_raw(ALARMS_KEY, &blob)?;
+    Ok(())
+}
+
+/* The chip's clock in the selected time zone */
+pub struct LocalClock<'a>(pub &'a TimeZone);
+
+impl Clock for LocalClock<'_> {
+    fn now(&self) -> i64 {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64;
+        timestamp + self.0.offset_at(timestamp) as i64
+    }
+}
+
+/* Passive buzzer, the LEDC timer sets the pitch */
+pub struct Buzzer {
+    channel: LedcDriver<'static>,
+    on: bool,
+}
+
+impl Buzzer {
+    pub fn new(mut channel: LedcDriver<'static>) -> Result<Self> {
+        channel.set_duty(0)?;
+        Ok(Buzzer { channel, on: false })
+    }
+
+    /* Beeps while `ringing`, `millis` paces the beeps */
+    pub fn update(&mut self, ringing: bool, millis: u64) -> Result<()> {
+        let on = ringing && (millis / BEEP_MS) % 2 == 0;
+        if on != self.on {
+            let duty = if on { self.channel.get_max_duty() / 2 } else { 0 };
+            self.channel.set_duty(duty)?;
+            self.on = on;
+        }
+        Ok(())
+    }
+}
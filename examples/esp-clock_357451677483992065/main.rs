@@ -17,11 +17,9 @@ use esp_idf_sys::link_patches;
 // Time stuff
 use esp_idf_svc::systime::EspSystemTime;
 
-use time::OffsetDateTime;
-use time::macros::offset;
+use time::{OffsetDateTime, UtcOffset};
+use tz::TimeZone;
 
-use esp_idf_svc::sntp;
-use esp_idf_svc::sntp::SyncStatus;
 
 // Graphic part
 use embedded_graphics::mono_font::MonoTextStyle;
@@ -36,12 +34,21 @@ use display_interface_spi::SPIInterfaceNoCS;
 use profont::{PROFONT_24_POINT, PROFONT_18_POINT};
 use tinybmp::Bmp;
 
+// BOOT button, Wi-Fi setup and time zone
+use esp_idf_hal::gpio::{PinDriver, Pull};
+use input::{Debouncer, Event, DEFAULT_DEBOUNCE_MS};
+
+// Alarms and buzzer
+use alarm::{Alarm, Weekdays};
+use esp_idf_hal::ledc::{config::TimerConfig, LedcDriver, LedcTimerDriver};
+
 // Wi-Fi
 use embedded_svc::wifi::*;
 use esp_idf_svc::eventloop::*;
 use esp_idf_svc::netif::*;
 use esp_idf_svc::wifi::*;
 use esp_idf_svc::wifi::EspWifi;
+use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs, NvsDefault};
 
 use esp_idf_svc::{
     log::EspLogger,
@@ -51,7 +58,11 @@ use esp_idf_svc::{
 use rustzx_core::zx::video::colors::ZXBrightness;
 use rustzx_core::zx::video::colors::ZXColor;
 
+mod alarms;
 mod display;
+mod provisioning;
+mod supervisor;
+mod timesync;
 
 
 const textStyle: TextStyle = TextStyleBuilder::new()
@@ -59,9 +70,46 @@ const textStyle: TextStyle = TextStyleBuilder::new()
     .baseline(embedded_graphics::text::Baseline::Middle)
     .build();
 
+/* Used until a network is chosen on the setup page (see provisioning.rs) */
 const WIFI_SSID: &str = "Wokwi-GUEST";
 const WIFI_PASS: &str = "";
 
+/* How long the clock waits for the network and the first time sync before it starts anyway */
+const NETWORK_WAIT: Duration = Duration::from_secs(30);
+
+static TIME_SYNC: timesync::Config = timesync::Config {
+    servers: &["pool.ntp.org", "time.google.com", "time.cloudflare.com"],
+    timeout: Duration::from_secs(15),
+    resync_interval: Duration::from_secs(6 * 3600),
+    retry_interval: Duration::from_secs(5 * 60),
+};
+
+/* POSIX TZ strings the BOOT button cycles through, the choice is kept in NVS (the first one
+ * until something is chosen).
+ * Offsets are hours west of UTC, DST rules are "month.week.weekday/time" (week 5 = last) */
+const TIMEZONES: [&str; 8] = [
+    "CET-1CEST,M3.5.0,M10.5.0/3",   // Central Europe
+    "GMT0BST,M3.5.0/1,M10.5.0",     // UK
+    "EET-2EEST,M3.5.0/3,M10.5.0/4", // Eastern Europe
+    "MSK-3",                        // Moscow
+    "<+0530>-5:30",                 // India
+    "AEST-10AEDT,M10.1.0,M4.1.0/3", // Sydney
+    "EST5EDT,M3.2.0,M11.1.0",       // US Eastern
+    "PST8PDT,M3.2.0,M11.1.0",       // US Pacific
+];
+
+const SETTINGS_NAMESPACE: &str = "clock";
+const ZONE_KEY: &str = "zone";
+
+/* Stored on the first start, later changes live in NVS (see alarms.rs) */
+const DEFAULT_ALARMS: [(u8, u8, Weekdays, &str); 2] = [
+    (7, 0, Weekdays::WORKDAYS, "Wake up"),
+    (9, 30, Weekdays::WEEKEND, "Weekend"),
+];
+
+/* Pitch of the buzzer */
+const BUZZER_FREQUENCY_KHZ: u32 = 2;
+
 fn main() -> Result<()> {
     esp_idf_sys::link_patches();
 
@@ -71,37 +119,102 @@ fn main() -> Result<()> {
     // Set up peripherals and display
     let peripherals = Peripherals::take().unwrap();
     let sysloop = EspSystemEventLoop::take()?;
+    let nvs = EspDefaultNvsPartition::take()?;
     let mut dp = display::create!(peripherals)?;
 
+    /* BOOT button: held during the logo it opens the Wi-Fi setup, later it switches the time zone */
+    let mut boot_button = PinDriver::input(peripherals.pins.gpio9)?;
+    boot_button.set_pull(Pull::Up)?;
+
+    /* Second button, dismisses a ringing alarm (BOOT snoozes it) */
+    let mut dismiss_button = PinDriver::input(peripherals.pins.gpio1)?;
+    dismiss_button.set_pull(Pull::Up)?;
+
+    let buzzer_timer = LedcTimerDriver::new(
+        peripherals.ledc.timer0,
+        &TimerConfig::new().frequency(BUZZER_FREQUENCY_KHZ.kHz().into()),
+    )?;
+    let mut buzzer = alarms::Buzzer::new(LedcDriver::new(
+        peripherals.ledc.channel0,
+        buzzer_timer,
+        peripherals.pins.gpio10,
+    )?)?;
+
+    let defaults = DEFAULT_ALARMS.map(|(hour, minute, weekdays, label)| Alarm::new(hour, minute, weekdays, label));
+    let mut alarms = alarms::load(nvs.clone(), &defaults)?;
+
     show_logo(&mut dp);
     wifi_image(&mut dp, false, display::color_conv);
 
+    let mut wifi = Box::new(EspWifi::new(peripherals.modem, sysloop.clone(), Some(nvs.clone()))?);
+
+    /* Unreadable credentials mustn't keep the clock from booting, BOOT still opens the setup */
+    let stored = provisioning::load(nvs.clone()).unwrap_or_else(|e| {
+        warn!("Stored Wi-Fi network unreadable: {:?}", e);
+        None
+    });
+    let provisioned = stored.is_some();
+    let (wifi_ssid, wifi_pass) = match stored {
+        Some(credentials) => (credentials.ssid, credentials.password),
+        None => (WIFI_SSID.to_string(), WIFI_PASS.to_string()),
+    };
+
+    if boot_button.is_low() {
+        info!("BOOT button held, starting Wi-Fi setup");
+        setupFlush(&mut dp, display::color_conv);
+        provisioning::run(&mut wifi, &sysloop, nvs.clone())?;
+    }
+
     wifi_connecting(&mut dp, false, display::color_conv);
-    info!(
-        "About to initialize WiFi (SSID: {}, PASS: {})",
-        WIFI_SSID, WIFI_PASS
-    );
+    /* The password may come from the setup page now, so it's not logged */
+    info!("About to initialize WiFi (SSID: {})", wifi_ssid);
+
+    /* A network chosen on the setup page is only out of reach for now, the supervisor keeps trying */
+    if let Err(e) = connect_wifi(&mut wifi, &sysloop, &wifi_ssid, &wifi_pass) {
+        if !provisioned {
+            warn!("{:?}, starting Wi-Fi setup", e);
+            wifi.stop()?;
+            setupFlush(&mut dp, display::color_conv);
+            provisioning::run(&mut wifi, &sysloop, nvs.clone())?;
+        }
+        warn!("{:?}, retrying in the background", e);
+    }
 
-    let mut wifi = wifi(peripherals.modem, sysloop.clone(), WIFI_SSID, WIFI_PASS)?;
+    let supervisor = supervisor::Supervisor::start(wifi, &sysloop)?;
+    let waiting = Instant::now();
+    while !supervisor.is_connected() && waiting.elapsed() < NETWORK_WAIT {
+        thread::sleep(Duration::from_millis(100));
+    }
 
-    wifi_connecting(&mut dp, true, display::color_conv);
+    let time_sync = timesync::TimeSync::start(&TIME_SYNC)?;
+    if supervisor.is_connected() {
+        wifi_connecting(&mut dp, true, display::color_conv);
+        if !time_sync.wait_for_first_sync(NETWORK_WAIT) {
+            warn!("Time not synced yet, running on the chip's own clock");
+        }
+    } else {
+        warn!("No network, running on the chip's own clock");
+        messageClear(&mut dp, display::color_conv);
+    }
 
     /* Unsafe section is used since it's required, if you're using C functions and datatypes */
     unsafe {
-        let sntp = sntp::EspSntp::new_default()?;
-        info!("SNTP initialized, waiting for status!");
-
-        while sntp.get_sync_status() != SyncStatus::Completed {}
-
-        info!("SNTP status received!");
 
         let timer: *mut time_t = ptr::null_mut();
 
         let mut timestamp = esp_idf_sys::time(timer);
 
-        let mut actual_date = OffsetDateTime::from_unix_timestamp(timestamp as i64)?
-            .to_offset(offset!(+2))
-            .date();
+        let mut zone_index = load_zone_index(nvs.clone()).unwrap_or_else(|e| {
+            warn!("Stored time zone unreadable: {:?}", e);
+            0
+        });
+        let mut timezone = TimeZone::parse(TIMEZONES[zone_index]).map_err(|e| anyhow!("Bad TZ string: {:?}", e))?;
+        info!("Time zone {}", TIMEZONES[zone_index]);
+
+        let mut zone_debouncer = Debouncer::new(DEFAULT_DEBOUNCE_MS);
+        let mut dismiss_debouncer = Debouncer::new(DEFAULT_DEBOUNCE_MS);
+
+        let mut actual_date = local_time(&timezone, timestamp as i64)?.date();
 
         info!(
             "{} - {} - {}",
@@ -128,19 +241,102 @@ fn main() -> Result<()> {
             display::color_conv,
         );
 
+        let mut zone_name = timezone.zone_at(timestamp as i64).name().to_string();
+        zoneFlush(&mut dp, &zone_name, display::color_conv);
+
         let i2c = peripherals.i2c0;
 
+        /* Polled often enough for the button, the screen is only redrawn when something changes */
+        let mut shown_time = String::new();
+        let mut shown_connected = true;
+        let mut shown_sync = String::new();
+        /* The alarm view covers the whole screen while an alarm rings */
+        let mut alarm_shown = false;
         loop {
+            let millis = EspSystemTime.now().as_millis() as u64;
+            let boot = zone_debouncer.update(boot_button.is_low(), millis);
+            let dismiss = dismiss_debouncer.update(dismiss_button.is_low(), millis);
+
+            if alarms.ringing().is_some() {
+                if boot == Event::Pressed {
+                    info!("Alarm snoozed");
+                    alarms.snooze(&alarms::LocalClock(&timezone));
+                } else if dismiss == Event::Pressed {
+                    info!("Alarm dismissed");
+                    alarms.dismiss();
+                }
+            } else if boot == Event::Pressed {
+                zone_index = (zone_index + 1) % TIMEZONES.len();
+                timezone = TimeZone::parse(TIMEZONES[zone_index]).map_err(|e| anyhow!("Bad TZ string: {:?}", e))?;
+                info!("Time zone {}", TIMEZONES[zone_index]);
+                store_zone_index(nvs.clone(), zone_index)?;
+            } else if dismiss == Event::Pressed && alarms.is_snoozed() {
+                info!("Snoozed alarm dismissed");
+                /* A one-shot alarm is off now */
+                if alarms.dismiss() {
+                    alarms::store(nvs.clone(), &alarms)?;
+                }
+            }
+
+            if let Some(index) = alarms.poll(&alarms::LocalClock(&timezone)) {
+                let alarm = alarms.alarm(index).unwrap();
+                info!("Alarm {:02}:{:02} {} ringing", alarm.hour, alarm.minute, alarm.label());
+                alarmFlush(&mut dp, alarm, display::color_conv);
+                alarm_shown = true;
+            }
+            buzzer.update(alarms.ringing().is_some(), millis)?;
+
+            if alarm_shown {
+                if alarms.ringing().is_some() {
+                    thread::sleep(Duration::from_millis(20));
+                    continue;
+                }
+                /* Snoozed, dismissed or rang out: back to the clock, a one-shot alarm is off now */
+                alarm_shown = false;
+                alarms::store(nvs.clone(), &alarms)?;
+
+                dp.clear(display::color_conv(ZXColor::White, ZXBrightness::Normal));
+                small_logo(&mut dp);
+                shown_connected = !supervisor.is_connected();
+                shown_time.clear();
+                shown_sync.clear();
+                zoneFlush(&mut dp, &zone_name, display::color_conv);
+                dateFlush(&mut dp, &date_str, display::color_conv);
+                weekdayFlush(
+                    &mut dp,
+                    &actual_date.weekday().to_string(),
+                    display::color_conv,
+                );
+            }
+
+            let connected = supervisor.is_connected();
+            if connected != shown_connected {
+                wifi_image(&mut dp, connected, display::color_conv);
+                shown_connected = connected;
+            }
+
             timestamp = esp_idf_sys::time(timer);
 
-            let mut rawTime =
-                OffsetDateTime::from_unix_timestamp(timestamp as i64)?.to_offset(offset!(+2));
+            let mut rawTime = local_time(&timezone, timestamp as i64)?;
 
-            timeFlush(
-                &mut dp,
-                &rawTime.time().to_string()[0..(rawTime.time().to_string().len() - 2)].to_string(),
-                display::color_conv,
-            );
+            let time_str = rawTime.time().to_string()[0..(rawTime.time().to_string().len() - 2)].to_string();
+            if time_str != shown_time {
+                timeFlush(&mut dp, &time_str, display::color_conv);
+                shown_time = time_str;
+            }
+
+            let sync_str = sync_age(time_sync.last_sync(), timestamp as i64);
+            if sync_str != shown_sync {
+                syncFlush(&mut dp, &sync_str, display::color_conv);
+                shown_sync = sync_str;
+            }
+
+            /* Changes at DST transitions too */
+            let zone = timezone.zone_at(timestamp as i64).name();
+            if zone != zone_name {
+                zone_name = zone.to_string();
+                zoneFlush(&mut dp, &zone_name, display::color_conv);
+            }
 
             if actual_date != rawTime.date() {
                 actual_date = rawTime.date();
@@ -160,12 +356,51 @@ fn main() -> Result<()> {
                 );
             }
 
-            thread::sleep(Duration::from_secs(1));
+            thread::sleep(Duration::from_millis(20));
         }
     }
     Ok(())
 }
 
+/* `timestamp` in the zone's local time, with the offset in effect at that moment */
+fn local_time(timezone: &TimeZone, timestamp: i64) -> anyhow::Result<OffsetDateTime> {
+    let offset = UtcOffset::from_whole_seconds(timezone.offset_at(timestamp))?;
+    Ok(OffsetDateTime::from_unix_timestamp(timestamp)?.to_offset(offset))
+}
+
+/* Index into `TIMEZONES` chosen with BOOT before, 0 if none was (or it's out of range now) */
+fn load_zone_index(nvs: EspDefaultNvsPartition) -> Result<usize> {
+    let storage = EspNvs::<NvsDefault>::new(nvs, SETTINGS_NAMESPACE, true)?;
+    let mut index = [0; 1];
+    match storage.get_raw(ZONE_KEY, &mut index)? {
+        Some(&[index]) if (index as usize) < TIMEZONES.len() => Ok(index as usize),
+        _ => Ok(0),
+    }
+}
+
+fn store_zone_index(nvs: EspDefaultNvsPartition, index: usize) -> Result<()> {
+    let mut storage = EspNvs::<NvsDefault>::new(nvs, SETTINGS_NAMESPACE, true)?;
+    storage.set_raw(ZONE_KEY, &[index as u8])?;
+    Ok(())
+}
+
+/* "NTP 5m": how long ago the time was synced, "NTP --" while it never was */
+fn sync_age(last_sync: Option<i64>, now: i64) -> String {
+    match last_sync {
+        None => "NTP --".to_string(),
+        Some(synced) => {
+            let minutes = (now - synced).max(0) / 60;
+            if minutes < 60 {
+                format!("NTP {}m", minutes)
+            } else if minutes < 48 * 60 {
+                format!("NTP {}h", minutes / 60)
+            } else {
+                format!("NTP {}d", minutes / (24 * 60))
+            }
+        }
+    }
+}
+
 fn timeFlush<D>(
     display: &mut D,
     toPrint: &String,
@@ -233,6 +468,77 @@ where
     Ok(())
 }
 
+/* Time sync indicator in the bottom-right corner */
+fn syncFlush<D>(
+    display: &mut D,
+    toPrint: &String,
+    color_conv: fn(ZXColor, ZXBrightness) -> D::Color,
+) -> anyhow::Result<()>
+where
+    D: DrawTarget + Dimensions,
+{
+    let size = display.bounding_box().size;
+    let (right, bottom) = (size.width as i32, size.height as i32);
+
+    Rectangle::new(Point::new(right - 130, bottom - 30), Size::new(130, 30))
+        .into_styled(
+            PrimitiveStyleBuilder::new()
+                .fill_color(color_conv(ZXColor::White, ZXBrightness::Normal))
+                .stroke_color(color_conv(ZXColor::White, ZXBrightness::Normal))
+                .stroke_width(1)
+                .build(),
+        )
+    .draw(display);
+
+    Text::with_alignment(
+        &toPrint,
+        Point::new(right - 5, bottom - 10),
+        MonoTextStyle::new(
+            &PROFONT_18_POINT,
+            color_conv(ZXColor::Black, ZXBrightness::Normal),
+        ),
+        Alignment::Right,
+    )
+    .draw(display);
+
+    Ok(())
+}
+
+/* Abbreviation of the zone in effect (CET/CEST...) in the top-right corner */
+fn zoneFlush<D>(
+    display: &mut D,
+    toPrint: &String,
+    color_conv: fn(ZXColor, ZXBrightness) -> D::Color,
+) -> anyhow::Result<()>
+where
+    D: DrawTarget + Dimensions,
+{
+    let right = display.bounding_box().size.width as i32;
+
+    Rectangle::new(Point::new(right - 130, 0), Size::new(130, 30))
+        .into_styled(
+            PrimitiveStyleBuilder::new()
+                .fill_color(color_conv(ZXColor::White, ZXBrightness::Normal))
+                .stroke_color(color_conv(ZXColor::White, ZXBrightness::Normal))
+                .stroke_width(1)
+                .build(),
+        )
+    .draw(display);
+
+    Text::with_alignment(
+        &toPrint,
+        Point::new(right - 5, 20),
+        MonoTextStyle::new(
+            &PROFONT_18_POINT,
+            color_conv(ZXColor::Black, ZXBrightness::Normal),
+        ),
+        Alignment::Right,
+    )
+    .draw(display);
+
+    Ok(())
+}
+
 fn weekdayFlush<D>(
     display: &mut D,
     toPrint: &String,
@@ -283,6 +589,16 @@ where
 
     /* than small */
     display.clear(display::color_conv(ZXColor::White, ZXBrightness::Normal).into());
+    small_logo(display);
+
+    Ok(())
+}
+
+/* Small logo in the bottom-left corner */
+fn small_logo<D>(display: &mut D) -> anyhow::Result<()>
+where
+    D: DrawTarget<Color = embedded_graphics::pixelcolor::Rgb565>  + Dimensions,
+{
     let bmp = Bmp::<Rgb565>::from_slice(include_bytes!("/home/esp/assets/esp-rs-small.bmp")).unwrap();
     Image::new(
         &bmp,
@@ -293,16 +609,49 @@ where
     Ok(())
 }
 
-fn wifi(
-    modem: impl esp_idf_hal::peripheral::Peripheral<P = esp_idf_hal::modem::Modem> + 'static,
-    sysloop: EspSystemEventLoop,
+/* Full-screen view of a ringing alarm: its label and time, and which button does what */
+fn alarmFlush<D>(
+    display: &mut D,
+    alarm: &Alarm,
+    color_conv: fn(ZXColor, ZXBrightness) -> D::Color,
+) -> anyhow::Result<()>
+where
+    D: DrawTarget<Color = embedded_graphics::pixelcolor::Rgb565>  + Dimensions,
+{
+    display.clear(color_conv(ZXColor::Yellow, ZXBrightness::Bright));
+
+    let center = display.bounding_box().center();
+    let big = MonoTextStyle::new(
+        &PROFONT_24_POINT,
+        color_conv(ZXColor::Black, ZXBrightness::Normal),
+    );
+    let small = MonoTextStyle::new(
+        &PROFONT_18_POINT,
+        color_conv(ZXColor::Black, ZXBrightness::Normal),
+    );
+
+    let label = if alarm.label().is_empty() { "Alarm" } else { alarm.label() };
+    Text::with_text_style(label, center - Size::new(0, 60), big, textStyle)
+        .draw(display);
+    Text::with_text_style(&format!("{:02}:{:02}", alarm.hour, alarm.minute), center - Size::new(0, 15), big, textStyle)
+        .draw(display);
+    Text::with_text_style("BOOT: snooze", center + Size::new(0, 45), small, textStyle)
+        .draw(display);
+    Text::with_text_style("GPIO1: dismiss", center + Size::new(0, 75), small, textStyle)
+        .draw(display);
+
+    Ok(())
+}
+
+/* Joins `wifi_ssid` as a station */
+fn connect_wifi(
+    wifi: &mut EspWifi<'static>,
+    sysloop: &EspSystemEventLoop,
     wifi_ssid : &str, 
     wifi_password : &str, 
-) -> Result<Box<EspWifi<'static>>> {
+) -> Result<()> {
     use std::net::Ipv4Addr;
 
-    let mut wifi = Box::new(EspWifi::new(modem, sysloop.clone(), None)?);
-
     info!("Wifi created, about to scan");
 
     let ap_infos = wifi.scan()?;
@@ -323,26 +672,22 @@ fn wifi(
         None
     };
 
-    wifi.set_configuration(&Configuration::Mixed(
+    let auth_method = if wifi_password.is_empty() { AuthMethod::None } else { AuthMethod::WPA2Personal };
+    wifi.set_configuration(&Configuration::Client(
         ClientConfiguration {
             ssid: wifi_ssid.into(),
             password: wifi_password.into(),
-            auth_method: AuthMethod::None,
+            auth_method,
             channel,
             ..Default::default()
         },
-        AccessPointConfiguration {
-            ssid: "aptest".into(),
-            channel: channel.unwrap_or(1),
-            ..Default::default()
-        },
     ))?;
 
     wifi.start()?;
 
     info!("Starting wifi...");
 
-    if !WifiWait::new(&sysloop)?
+    if !WifiWait::new(sysloop)?
         .wait_with_timeout(Duration::from_secs(20), || wifi.is_started().unwrap())
     {
         bail!("Wifi did not start");
@@ -352,7 +697,7 @@ fn wifi(
 
     wifi.connect()?;
 
-    if !EspNetifWait::new::<EspNetif>(wifi.sta_netif(), &sysloop)?.wait_with_timeout(
+    if !EspNetifWait::new::<EspNetif>(wifi.sta_netif(), sysloop)?.wait_with_timeout(
         Duration::from_secs(20),
         || {
             wifi.is_connected().unwrap()
@@ -366,8 +711,67 @@ fn wifi(
 
     info!("Wifi DHCP info: {:?}", ip_info);
 
-    Ok(wifi)
+    Ok(())
 }
+/* Clears the message band in the middle of the screen */
+fn messageClear<D>(
+    display: &mut D,
+    color_conv: fn(ZXColor, ZXBrightness) -> D::Color,
+) -> anyhow::Result<()>
+where
+    D: DrawTarget + Dimensions,
+{
+    Rectangle::with_center(
+        display.bounding_box().center(),
+        Size::new(display.bounding_box().size.width, 80),
+    )
+    .into_styled(
+        PrimitiveStyleBuilder::new()
+            .fill_color(color_conv(ZXColor::White, ZXBrightness::Normal))
+            .stroke_color(color_conv(ZXColor::White, ZXBrightness::Normal))
+            .stroke_width(1)
+            .build(),
+    )
+    .draw(display);
+
+    Ok(())
+}
+
+/* Tells how to reach the setup page, see provisioning.rs */
+fn setupFlush<D>(
+    display: &mut D,
+    color_conv: fn(ZXColor, ZXBrightness) -> D::Color,
+) -> anyhow::Result<()>
+where
+    D: DrawTarget<Color = embedded_graphics::pixelcolor::Rgb565>  + Dimensions,
+{
+    Rectangle::with_center(
+        display.bounding_box().center(),
+        Size::new(display.bounding_box().size.width, 80),
+    )
+    .into_styled(
+        PrimitiveStyleBuilder::new()
+            .fill_color(color_conv(ZXColor::White, ZXBrightness::Normal))
+            .stroke_color(color_conv(ZXColor::White, ZXBrightness::Normal))
+            .stroke_width(1)
+            .build(),
+    )
+    .draw(display);
+
+    let style = MonoTextStyle::new(
+        &PROFONT_18_POINT,
+        color_conv(ZXColor::Black, ZXBrightness::Normal),
+    );
+    Text::with_text_style("Wi-Fi setup: join", display.bounding_box().center() - Size::new(0, 25), style, textStyle)
+        .draw(display);
+    Text::with_text_style(provisioning::AP_SSID, display.bounding_box().center(), style, textStyle)
+        .draw(display);
+    Text::with_text_style("and open any page", display.bounding_box().center() + Size::new(0, 25), style, textStyle)
+        .draw(display);
+
+    Ok(())
+}
+
 /* if this bool is true => wifi connected */
 fn wifi_connecting<D>(
     display: &mut D,
@@ -444,19 +848,21 @@ fn wifi_image<D>(
 where
     D: DrawTarget<Color = embedded_graphics::pixelcolor::Rgb565>  + Dimensions,
 {
+    /* The icons get swapped while the clock runs, clear the previous one */
+    Rectangle::new(
+        Point::new(50, display.bounding_box().size.height as i32 - 50),
+        Size::new(50, 50),
+    )
+    .into_styled(
+        PrimitiveStyleBuilder::new()
+            .fill_color(color_conv(ZXColor::White, ZXBrightness::Normal))
+            .stroke_color(color_conv(ZXColor::White, ZXBrightness::Normal))
+            .stroke_width(1)
+            .build(),
+    )
+    .draw(display);
+
     if wifi {
-        Rectangle::new(
-            Point::new(50, display.bounding_box().size.height as i32 - 50),
-            Size::new(50, 50),
-        )
-        .into_styled(
-            PrimitiveStyleBuilder::new()
-                .fill_color(color_conv(ZXColor::White, ZXBrightness::Normal))
-                .stroke_color(color_conv(ZXColor::White, ZXBrightness::Normal))
-                .stroke_width(1)
-                .build(),
-        )
-        .draw(display);
         let bmp = Bmp::<Rgb565>::from_slice(include_bytes!("/home/esp/assets/wifi.bmp")).unwrap();
         Image::new(
             &bmp,
@@ -0,0 +1,281 @@
+/* Wi-Fi provisioning.
+ *
+ * The clock opens its own access point (`AP_SSID`, no password) and serves a page listing the
+ * networks it can see, with a form for the password. Every DNS name resolves to the clock, so
+ * phones and laptops pop the page up by themselves (captive portal). The chosen network is
+ * stored in NVS and the clock restarts, connecting to it as a station from then on. */
+
+use std::net::{Ipv4Addr, UdpSocket};
+use std::sync::mpsc;
+use std::{thread, time::Duration};
+
+use std::result::Result::Ok;
+
+use anyhow::*;
+use log::*;
+
+use embedded_svc::http::Method;
+use embedded_svc::io::{Read, Write};
+use embedded_svc::wifi::*;
+use esp_idf_svc::eventloop::EspSystemEventLoop;
+use esp_idf_svc::http::server::{Configuration as HttpConfiguration, EspHttpServer};
+use esp_idf_svc::nvs::{EspDefaultNvsPartition, EspNvs, NvsDefault};
+use esp_idf_svc::wifi::{EspWifi, WifiWait};
+
+pub const AP_SSID: &str = "esp-clock-setup";
+
+const NVS_NAMESPACE: &str = "wifi";
+const SSID_KEY: &str = "ssid";
+const PASSWORD_KEY: &str = "password";
+
+/* Limits of the Wi-Fi driver, in bytes (the page's maxlength counts characters) */
+const MAX_SSID_LEN: usize = 32;
+const MAX_PASSWORD_LEN: usize = 64;
+
+/* Longest form we accept, SSID (32) and password (64) percent-encoded plus the field names */
+const MAX_FORM_LENGTH: usize = 512;
+
+pub struct Credentials {
+    pub ssid: String,
+    pub password: String,
+}
+
+/* Network stored by a previous provisioning, if any */
+pub fn load(nvs: EspDefaultNvsPartition) -> Result<Option<Credentials>> {
+    let storage = EspNvs::<NvsDefault>::new(nvs, NVS_NAMESPACE, true)?;
+    let mut ssid = [0; MAX_SSID_LEN];
+    let mut password = [0; MAX_PASSWORD_LEN];
+
+    /* Not lossy, replacement characters could push them over the driver's limits */
+    match (storage.get_raw(SSID_KEY, &mut ssid)?, storage.get_raw(PASSWORD_KEY, &mut password)?) {
+        (Some(ssid), Some(password)) if !ssid.is_empty() => Ok(Some(Credentials {
+            ssid: String::from_utf8(ssid.to_vec())?,
+            password: String::from_utf8(password.to_vec())?,
+        })),
+        _ => Ok(None),
+    }
+}
+
+pub fn store(nvs: EspDefaultNvsPartition, credentials: &Credentials) -> Result<()> {
+    ensure!(
+        credentials.ssid.len() <= MAX_SSID_LEN && credentials.password.len() <= MAX_PASSWORD_LEN,
+        "Wi-Fi network name or password too long"
+    );
+    let mut storage = EspNvs::<NvsDefault>::new(nvs, NVS_NAMESPACE, true)?;
+    storage.set_raw(SSID_KEY, credentials.ssid.as_bytes())?;
+    storage.set_raw(PASSWORD_KEY, credentials.password.as_bytes())?;
+    Ok(())
+}
+
+/* Serves the setup page until a network is chosen, then stores it and restarts the chip */
+pub fn run(wifi: &mut EspWifi<'static>, sysloop: &EspSystemEventLoop, nvs: EspDefaultNvsPartition) -> Result<()> {
+    /* Mixed, the station part is needed to scan */
+    wifi.set_configuration(&Configuration::Mixed(
+        ClientConfiguration::default(),
+        AccessPointConfiguration {
+            ssid: AP_SSID.into(),
+            auth_method: AuthMethod::None,
+            ..Default::default()
+        },
+    ))?;
+    wifi.start()?;
+
+    if !WifiWait::new(sysloop)?
+        .wait_with_timeout(Duration::from_secs(20), || wifi.is_started().unwrap())
+    {
+        bail!("Wifi did not start");
+    }
+
+    let mut networks = wifi.scan()?;
+    networks.sort_by(|a, b| b.signal_strength.cmp(&a.signal_strength));
+    networks.dedup_by(|a, b| a.ssid == b.ssid);
+    info!("Provisioning, {} networks around", networks.len());
+
+    let ip = wifi.ap_netif().get_ip_info()?.ip;
+    info!("Setup page on http://{}/ (access point {})", ip, AP_SSID);
+
+    let page = setup_page(&networks);
+    let (chosen, choice) = mpsc::channel::<Credentials>();
+
+    let mut server = EspHttpServer::new(&HttpConfiguration {
+        uri_match_wildcard: true,
+        ..Default::default()
+    })?;
+
+    server.fn_handler("/", Method::Get, move |request| {
+        request.into_ok_response()?.write_all(page.as_bytes())?;
+        Ok(())
+    })?;
+
+    server.fn_handler("/connect", Method::Post, move |mut request| {
+        let mut form = [0; MAX_FORM_LENGTH];
+        let mut length = 0;
+        /* Only a body that fits whole is used, a cut off one could end in a truncated password */
+        let complete = loop {
+            if length == form.len() {
+                break request.read(&mut [0; 1])? == 0;
+            }
+            match request.read(&mut form[length..])? {
+                0 => break true,
+                read => length += read,
+            }
+        };
+        if !complete {
+            let mut response = request.into_response(413, Some("Payload Too Large"), &[])?;
+            response.write_all(b"<p>That form is too long, go back and check the network name and password.</p>")?;
+            return Ok(());
+        }
+
+        let form = String::from_utf8_lossy(&form[..length]).into_owned();
+        let credentials = Credentials {
+            ssid: form_field(&form, "ssid").unwrap_or_default(),
+            password: form_field(&form, "password").unwrap_or_default(),
+        };
+
+        let mut response = request.into_ok_response()?;
+        if credentials.ssid.is_empty() {
+            response.write_all(b"<p>No network chosen, go back and pick one.</p>")?;
+        } else if credentials.ssid.len() > MAX_SSID_LEN || credentials.password.len() > MAX_PASSWORD_LEN {
+            response.write_all(
+                format!(
+                    "<p>Network names are at most {} bytes and passwords {}, go back and check them.</p>",
+                    MAX_SSID_LEN, MAX_PASSWORD_LEN
+                )
+                .as_bytes(),
+            )?;
+        } else {
+            response.write_all(
+                format!("<p>Connecting to {}, the clock restarts now.</p>", escape_html(&credentials.ssid)).as_bytes(),
+            )?;
+            chosen.send(credentials).unwrap();
+        }
+        Ok(())
+    })?;
+
+    /* Connectivity checks (generate_204, hotspot-detect.html, ...) get sent to the setup page */
+    let location = format!("http://{}/", ip);
+    server.fn_handler("/*", Method::Get, move |request| {
+        request.into_response(302, Some("Found"), &[("Location", &location)])?;
+        Ok(())
+    })?;
+
+    thread::Builder::new()
+        .stack_size(4096)
+        .spawn(move || {
+            if let Err(e) = answer_dns(ip) {
+                error!("Captive portal DNS stopped: {:?}", e);
+            }
+        })?;
+
+    let credentials = choice.recv()?;
+    info!("Network {} chosen, restarting", credentials.ssid);
+    store(nvs, &credentials)?;
+
+    /* Let the response reach the browser */
+    thread::sleep(Duration::from_secs(2));
+    drop(server);
+    esp_idf_hal::reset::restart();
+}
+
+fn setup_page(networks: &[AccessPointInfo]) -> String {
+    let mut options = String::new();
+    for network in networks.iter().filter(|network| !network.ssid.is_empty()) {
+        let ssid = escape_html(&network.ssid);
+        let lock = if network.auth_method == AuthMethod::None { "" } else { " &#128274;" };
+        options += &format!(
+            "<label><input type=\"radio\" name=\"ssid\" value=\"{}\"> {} ({} dBm){}</label><br>",
+            ssid, ssid, network.signal_strength, lock
+        );
+    }
+    if options.is_empty() {
+        options += "<p>No networks found, type the name below.</p>";
+    }
+
+    format!(
+        "<!DOCTYPE html><html><head><meta name=\"viewport\" content=\"width=device-width\">\
+         <title>esp-clock setup</title></head><body><h2>esp-clock Wi-Fi</h2>\
+         <form method=\"post\" action=\"/connect\">{}\
+         <p><label>Other network <input name=\"ssid\" maxlength=\"32\"></label></p>\
+         <p><label>Password <input name=\"password\" type=\"password\" maxlength=\"64\"></label></p>\
+         <p><button type=\"submit\">Connect</button></p></form></body></html>",
+        options
+    )
+}
+
+/* Value of `name` in an application/x-www-form-urlencoded body, the last non-empty one wins
+ * (the "other network" field comes after the list) */
+fn form_field(form: &str, name: &str) -> Option<String> {
+    form.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .filter(|(key, value)| *key == name && !value.is_empty())
+        .map(|(_, value)| url_decode(value))
+        .last()
+}
+
+fn url_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => match value.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                Some(byte) => {
+                    decoded.push(byte);
+                    i += 2;
+                }
+                None => decoded.push(b'%'),
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/* Answers every A query with the clock's address */
+fn answer_dns(ip: Ipv4Addr) -> Result<()> {
+    let socket = UdpSocket::bind("0.0.0.0:53")?;
+    let mut query = [0; 512];
+    loop {
+        let (length, client) = socket.recv_from(&mut query)?;
+        if let Some(reply) = dns_reply(&query[..length], ip) {
+            socket.send_to(&reply, client)?;
+        }
+    }
+}
+
+fn dns_reply(query: &[u8], ip: Ipv4Addr) -> Option<Vec<u8>> {
+    /* Header, then a single question: labels up to a zero byte, type and class */
+    let is_query = query.len() > 12 && query[2] & 0x80 == 0 && query[4..6] == [0, 1];
+    if !is_query {
+        return None;
+    }
+    let mut end = 12;
+    while *query.get(end)? != 0 {
+        end += 1 + query[end] as usize;
+    }
+    let question_end = end + 5;
+    let question = query.get(12..question_end)?;
+    let is_a = question[question.len() - 4..question.len() - 2] == [0, 1];
+
+    let mut reply = Vec::with_capacity(question_end + 16);
+    reply.extend_from_slice(&query[0..2]);
+    /* Response, authoritative, recursion desired copied from the query, recursion available */
+    reply.extend_from_slice(&[0x84 | (query[2] & 0x01), 0x80]);
+    reply.extend_from_slice(&[0, 1, 0, is_a as u8, 0, 0, 0, 0]);
+    reply.extend_from_slice(question);
+    if is_a {
+        /* Name pointing back at the question, A, IN, TTL 60 s, 4 bytes of address */
+        reply.extend_from_slice(&[0xC0, 12, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4]);
+        reply.extend_from_slice(&ip.octets());
+    }
+    Some(reply)
+}
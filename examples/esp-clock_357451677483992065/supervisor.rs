@@ -0,0 +1,132 @@
+/* Keeps the station connected.
+ *
+ * Wi-Fi and IP events from the system event loop are forwarded to a thread that owns the
+ * driver. When the link goes down it calls `connect` again after 1 s, then 2 s, 4 s... up to
+ * `MAX_RETRY_DELAY`, and starts over at 1 s once an address is assigned. The main loop only
+ * reads `is_connected` (for the icon on screen). */
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use std::result::Result::Ok;
+
+use anyhow::*;
+use log::*;
+
+use esp_idf_svc::eventloop::{EspSubscription, EspSystemEventLoop, System};
+use esp_idf_svc::netif::IpEvent;
+use esp_idf_svc::wifi::{EspWifi, WifiEvent};
+
+const FIRST_RETRY_DELAY: Duration = Duration::from_secs(1);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/* How long the supervisor sleeps when there's nothing to retry */
+const IDLE: Duration = Duration::from_secs(3600);
+
+enum Link {
+    Up,
+    Down,
+}
+
+pub struct Supervisor {
+    connected: Arc<AtomicBool>,
+    /* Unsubscribed when dropped */
+    _wifi_events: EspSubscription<System>,
+    _ip_events: EspSubscription<System>,
+}
+
+impl Supervisor {
+    /* Takes over `wifi` (started, connected or not) and reconnects it whenever needed */
+    pub fn start(wifi: Box<EspWifi<'static>>, sysloop: &EspSystemEventLoop) -> Result<Self> {
+        let connected = Arc::new(AtomicBool::new(wifi.is_connected()?));
+        let (link, events) = mpsc::channel();
+
+        let wifi_link = link.clone();
+        let _wifi_events = sysloop.subscribe(move |event: &WifiEvent| {
+            if let WifiEvent::StaDisconnected = event {
+                wifi_link.send(Link::Down).ok();
+            }
+        })?;
+        let _ip_events = sysloop.subscribe(move |event: &IpEvent| {
+            if let IpEvent::DhcpIpAssigned(_) = event {
+                link.send(Link::Up).ok();
+            }
+        })?;
+
+        let status = connected.clone();
+        thread::Builder::new()
+            .stack_size(4096)
+            .spawn(move || supervise(wifi, events, status))?;
+
+        Ok(Supervisor { connected, _wifi_events, _ip_events })
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+}
+
+fn supervise(mut wifi: Box<EspWifi<'static>>, events: mpsc::Receiver<Link>, connected: Arc<AtomicBool>) {
+    let mut backoff = Backoff::new(FIRST_RETRY_DELAY, MAX_RETRY_DELAY);
+    let mut retry_at = if connected.load(Ordering::Relaxed) {
+        None
+    } else {
+        Some(Instant::now() + backoff.next_delay())
+    };
+
+    loop {
+        let timeout = retry_at.map_or(IDLE, |at| at.saturating_duration_since(Instant::now()));
+        match events.recv_timeout(timeout) {
+            Ok(Link::Up) => {
+                info!("Wi-Fi connected");
+                connected.store(true, Ordering::Relaxed);
+                backoff.reset();
+                retry_at = None;
+            }
+            Ok(Link::Down) => {
+                connected.store(false, Ordering::Relaxed);
+                /* Failed attempts are reported as disconnects too, they don't bring the retry closer */
+                if retry_at.is_none() {
+                    let delay = backoff.next_delay();
+                    warn!("Wi-Fi disconnected, reconnecting in {:?}", delay);
+                    retry_at = Some(Instant::now() + delay);
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                if retry_at.is_some() {
+                    info!("Reconnecting Wi-Fi");
+                    if let Err(e) = wifi.connect() {
+                        warn!("Wi-Fi connect failed: {:?}", e);
+                    }
+                    retry_at = Some(Instant::now() + backoff.next_delay());
+                }
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+/* Doubling delays between `first` and `max` */
+struct Backoff {
+    first: Duration,
+    max: Duration,
+    next: Duration,
+}
+
+impl Backoff {
+    fn new(first: Duration, max: Duration) -> Self {
+        Backoff { first, max, next: first }
+    }
+
+    fn next_delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = (self.next * 2).min(self.max);
+        delay
+    }
+
+    fn reset(&mut self) {
+        self.next = self.first;
+    }
+}
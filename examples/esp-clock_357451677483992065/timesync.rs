@@ -0,0 +1,111 @@
+/* Sets the system clock over SNTP.
+ *
+ * A background thread asks the configured servers in turn, giving each one `timeout` to
+ * answer, and syncs again every `resync_interval` (every `retry_interval` while no server
+ * answers). Between syncs, or when there is no network at all, the chip's own clock keeps
+ * running, so the display never waits for the network. `last_sync` tells how fresh it is. */
+
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use std::result::Result::Ok;
+
+use anyhow::*;
+use log::*;
+
+use esp_idf_svc::sntp::{EspSntp, SntpConf, SyncStatus};
+
+pub struct Config {
+    /* Tried in this order, the next one when a server doesn't answer within `timeout` */
+    pub servers: &'static [&'static str],
+    pub timeout: Duration,
+    pub resync_interval: Duration,
+    pub retry_interval: Duration,
+}
+
+pub struct TimeSync {
+    /* Unix timestamp of the last successful sync, 0 before the first one */
+    last_sync: Arc<AtomicI64>,
+}
+
+impl TimeSync {
+    pub fn start(config: &'static Config) -> Result<Self> {
+        if config.servers.is_empty() {
+            bail!("No SNTP servers configured");
+        }
+
+        let last_sync = Arc::new(AtomicI64::new(0));
+        let synced = last_sync.clone();
+        thread::Builder::new()
+            .stack_size(4096)
+            .spawn(move || keep_in_sync(config, &synced))?;
+
+        Ok(TimeSync { last_sync })
+    }
+
+    pub fn last_sync(&self) -> Option<i64> {
+        match self.last_sync.load(Ordering::Relaxed) {
+            0 => None,
+            timestamp => Some(timestamp),
+        }
+    }
+
+    /* Waits at most `timeout` for the first sync, returns whether it happened */
+    pub fn wait_for_first_sync(&self, timeout: Duration) -> bool {
+        let started = Instant::now();
+        while self.last_sync().is_none() {
+            if started.elapsed() >= timeout {
+                return false;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+        true
+    }
+}
+
+fn keep_in_sync(config: &Config, last_sync: &AtomicI64) {
+    let mut server = 0;
+    loop {
+        let delay = match sync_once(config.servers[server], config.timeout) {
+            Ok(true) => {
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+                last_sync.store(now.as_secs() as i64, Ordering::Relaxed);
+                info!("Time synced from {}", config.servers[server]);
+                config.resync_interval
+            }
+            result => {
+                if let Err(e) = result {
+                    warn!("SNTP client for {} failed: {:?}", config.servers[server], e);
+                } else {
+                    warn!("No answer from {} within {:?}", config.servers[server], config.timeout);
+                }
+                server = (server + 1) % config.servers.len();
+                /* Straight to the next server, a pause once all of them were tried */
+                if server != 0 {
+                    continue;
+                }
+                config.retry_interval
+            }
+        };
+        thread::sleep(delay);
+    }
+}
+
+/* Runs an SNTP client for `server` until it syncs (true) or `timeout` passes (false) */
+fn sync_once(server: &str, timeout: Duration) -> Result<bool> {
+    let mut conf = SntpConf::default();
+    conf.servers[0] = server;
+
+    /* Stopped again when dropped, the chip's clock runs on by itself */
+    let sntp = EspSntp::new(&conf)?;
+    let started = Instant::now();
+    while started.elapsed() < timeout {
+        if sntp.get_sync_status() == SyncStatus::Completed {
+            return Ok(true);
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    Ok(false)
+}
@@ -0,0 +1,208 @@
+/* Gallery pictures.
+ *
+ * An artwork draws itself with a `Plotter` onto a `Canvas`, which every Rgb565 `DrawTarget`
+ * (the display, a framebuffer, a simulator window) is. `&mut dyn Canvas` is a `DrawTarget`
+ * again, so animations use embedded-graphics as usual (`.draw(&mut canvas)`). New pictures
+ * implement `Artwork` and get one line in `DRAWINGS` with the palette to draw them in, or are
+ * SVG files in assets/ (see build.rs and vector.rs). The menu is built from `ARTWORKS`.
+ *
+ * `Rendering` draws an artwork a few samples at a time, so the caller can show progress and
+ * stop in between, optionally through a `View` (zoomed in and panned). */
+
+use core::fmt::Debug;
+
+use embedded_graphics::{pixelcolor::Rgb565, prelude::*, primitives::Rectangle};
+
+use crate::drawings::{Eye, Garden, LollipopGuy};
+use crate::palette::{Ink, Palette, PAPER};
+use crate::plot::{Plotter, Stroke, View};
+use crate::vector::VECTOR_ARTWORKS;
+
+/* An artwork as it's shown: what to draw and in which colors */
+#[derive(Clone, Copy)]
+pub struct Exhibit {
+    pub artwork: &'static dyn Artwork,
+    pub palette: &'static Palette,
+}
+
+/* Drawn in code, followed in the menu by the ones converted from assets/ (on `PAPER`) */
+const DRAWINGS: [Exhibit; 3] = [
+    Exhibit { artwork: &Eye, palette: &EYE_COLORS },
+    Exhibit { artwork: &LollipopGuy, palette: &LOLLIPOP_COLORS },
+    Exhibit { artwork: &Garden, palette: &GARDEN_COLORS },
+];
+
+/* Outer spiral, iris */
+const EYE_COLORS: Palette = Palette {
+    background: Rgb565::WHITE,
+    inks: &[
+        Ink::Gradient(Rgb565::CSS_NAVY, Rgb565::CSS_DODGER_BLUE),
+        Ink::Gradient(Rgb565::CSS_SADDLE_BROWN, Rgb565::CSS_FOREST_GREEN),
+    ],
+};
+
+/* Body, head, eyes, hand, lollipop */
+const LOLLIPOP_COLORS: Palette = Palette {
+    background: Rgb565::CSS_IVORY,
+    inks: &[
+        Ink::Solid(Rgb565::CSS_DODGER_BLUE),
+        Ink::Solid(Rgb565::CSS_DARK_ORANGE),
+        Ink::Solid(Rgb565::BLACK),
+        Ink::Solid(Rgb565::CSS_DARK_ORANGE),
+        Ink::Gradient(Rgb565::CSS_CRIMSON, Rgb565::CSS_HOT_PINK),
+    ],
+};
+
+/* Stems, then the blossoms from left to right (the big one last) */
+const GARDEN_COLORS: Palette = Palette {
+    background: Rgb565::CSS_HONEYDEW,
+    inks: &[
+        Ink::Solid(Rgb565::CSS_FOREST_GREEN),
+        Ink::Solid(Rgb565::CSS_CRIMSON),
+        Ink::Solid(Rgb565::CSS_GOLD),
+        Ink::Solid(Rgb565::CSS_MEDIUM_PURPLE),
+        Ink::Solid(Rgb565::CSS_DARK_ORANGE),
+        Ink::Solid(Rgb565::CSS_DODGER_BLUE),
+        Ink::Gradient(Rgb565::CSS_HOT_PINK, Rgb565::CSS_PURPLE),
+    ],
+};
+
+pub const ARTWORK_COUNT: usize = DRAWINGS.len() + VECTOR_ARTWORKS.len();
+
+pub static ARTWORKS: [Exhibit; ARTWORK_COUNT] = registry();
+
+const fn registry() -> [Exhibit; ARTWORK_COUNT] {
+    let mut all = [DRAWINGS[0]; ARTWORK_COUNT];
+    let mut i = 0;
+    while i < all.len() {
+        all[i] = if i < DRAWINGS.len() {
+            DRAWINGS[i]
+        } else {
+            Exhibit { artwork: VECTOR_ARTWORKS[i - DRAWINGS.len()], palette: &PAPER }
+        };
+        i += 1;
+    }
+    all
+}
+
+pub trait Artwork: Sync {
+    fn name(&self) -> &'static str;
+
+    /* Has to plot the same samples every time, `Rendering` relies on it to resume. The
+     * background is already filled in when it's called */
+    fn draw(&self, plotter: &mut Plotter, palette: &Palette);
+
+    /* Called every `ANIMATION_INTERVAL_MS` while the artwork is shown, `frame` counts from 0.
+     * Returns `false` if the artwork isn't animated (then it isn't called again) */
+    fn animate(&self, _canvas: &mut dyn Canvas, _frame: u32) -> bool {
+        false
+    }
+}
+
+pub const ANIMATION_INTERVAL_MS: u64 = 100;
+
+/* Stroke a plotter starts with, artworks normally set their own */
+pub const DEFAULT_STROKE: Stroke = Stroke::new(1, Ink::Solid(Rgb565::BLACK));
+
+pub struct Rendering {
+    exhibit: Exhibit,
+    view: View,
+    done: u32,
+    /* Known after the first step */
+    total: Option<u32>,
+}
+
+impl Rendering {
+    pub fn new(exhibit: Exhibit) -> Self {
+        Rendering {
+            exhibit,
+            view: View::WHOLE,
+            done: 0,
+            total: None,
+        }
+    }
+
+    pub fn with_view(mut self, view: View) -> Self {
+        self.view = view;
+        self
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.total == Some(self.done)
+    }
+
+    /* Plots the next `samples` samples, returns `true` once the artwork is complete. The
+     * first step fills the background */
+    pub fn step(&mut self, canvas: &mut dyn Canvas, samples: u32) -> bool {
+        if self.total.is_none() {
+            canvas.fill_solid(&canvas.bounds(), self.exhibit.palette.background);
+        }
+
+        let end = self.done.saturating_add(samples);
+        let mut plotter = Plotter::new(canvas, DEFAULT_STROKE)
+            .with_window(self.done..end)
+            .with_view(self.view);
+        self.exhibit.artwork.draw(&mut plotter, self.exhibit.palette);
+
+        let total = plotter.samples();
+        self.total = Some(total);
+        self.done = end.min(total);
+        self.is_done()
+    }
+
+    /* (samples drawn, samples in total), the total is 0 before the first step */
+    pub fn progress(&self) -> (u32, u32) {
+        (self.done, self.total.unwrap_or(0))
+    }
+}
+
+/* Object safe part of `DrawTarget`, drawing errors are treated as fatal (unwrapped) */
+pub trait Canvas {
+    fn draw_pixels(&mut self, pixels: &mut dyn Iterator<Item = Pixel<Rgb565>>);
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Rgb565);
+
+    fn bounds(&self) -> Rectangle;
+}
+
+impl<D> Canvas for D
+where
+    D: DrawTarget<Color = Rgb565>,
+    D::Error: Debug,
+{
+    fn draw_pixels(&mut self, pixels: &mut dyn Iterator<Item = Pixel<Rgb565>>) {
+        self.draw_iter(pixels).unwrap();
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Rgb565) {
+        DrawTarget::fill_solid(self, area, color).unwrap();
+    }
+
+    fn bounds(&self) -> Rectangle {
+        self.bounding_box()
+    }
+}
+
+impl Dimensions for &mut (dyn Canvas + '_) {
+    fn bounding_box(&self) -> Rectangle {
+        (**self).bounds()
+    }
+}
+
+impl DrawTarget for &mut (dyn Canvas + '_) {
+    type Color = Rgb565;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Rgb565>>,
+    {
+        (**self).draw_pixels(&mut pixels.into_iter());
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Rgb565) -> Result<(), Self::Error> {
+        (**self).fill_solid(area, color);
+        Ok(())
+    }
+}
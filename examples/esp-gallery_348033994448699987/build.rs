@@ -0,0 +1,40 @@
+/* Turns the SVG pictures in assets/ into const tables of drawing commands (vector_artworks.rs
+ * in OUT_DIR, included by vector.rs), every file becomes one artwork in the gallery.
+ *
+ * The conversion is in the `svgart` crate (host tested), which lists the subset of SVG that is
+ * understood; anything else fails the build rather than giving a silently wrong picture. */
+
+use std::{env, fmt::Write as _, fs, path::PathBuf};
+
+const ASSETS: &str = "assets";
+
+fn main() {
+    println!("cargo:rerun-if-changed={}", ASSETS);
+
+    let mut files: Vec<PathBuf> = match fs::read_dir(ASSETS) {
+        Ok(entries) => entries
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("svg"))
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    files.sort();
+
+    let mut out = String::new();
+    writeln!(out, "pub const VECTOR_ARTWORKS: [&dyn Artwork; {}] = [", files.len()).unwrap();
+    for file in &files {
+        let source = fs::read_to_string(file).unwrap();
+        let (name, commands) = svgart::convert(file, &source).unwrap_or_else(|err| panic!("{}: {}", file.display(), err));
+        println!("cargo:rerun-if-changed={}", file.display());
+
+        writeln!(out, "    &VectorArt {{\n        name: {:?},\n        commands: &[", name).unwrap();
+        for command in commands {
+            writeln!(out, "            Command::{},", command).unwrap();
+        }
+        writeln!(out, "        ],\n    }},").unwrap();
+    }
+    writeln!(out, "];").unwrap();
+
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    fs::write(out_dir.join("vector_artworks.rs"), out).unwrap();
+}
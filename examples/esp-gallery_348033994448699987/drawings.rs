@@ -0,0 +1,148 @@
+use core::f32::consts::PI;
+
+use embedded_graphics::prelude::*;
+
+use crate::artwork::Artwork;
+use crate::palette::Palette;
+use crate::plot::{Plotter, Stroke};
+use crate::sample::{cos, real, sin, Linear, Real};
+
+/* About as thick as the text glyphs the pictures used to be made of */
+const PEN_WIDTH: u32 = 2;
+const BLOSSOM_WIDTH: u32 = 3;
+
+/* Inks: outer spiral, iris */
+pub struct Eye;
+
+impl Artwork for Eye {
+    fn name(&self) -> &'static str {
+        "Eye"
+    }
+
+    fn draw(&self, plotter: &mut Plotter, palette: &Palette) {
+        /* vt = i / (20 PI), the angle of the spirals and, shifted, their radius */
+        let vt = Linear::new(1.0 / (20.0 * PI as f64));
+        let (x, y) = (Real::from(160), Real::from(125));
+
+        plotter.set_stroke(Stroke::new(PEN_WIDTH, palette.ink(0)));
+        plotter.curve(0..8000, |i| {
+            let r = vt.value(i) - Real::from(50);
+            (r * sin(vt.angle(i)) + x, r * cos(vt.angle(i)) + y)
+        });
+        plotter.set_stroke(Stroke::new(PEN_WIDTH, palette.ink(1)));
+        plotter.curve(8000..13200, |i| {
+            (
+                (vt.value(i) + Real::from(20)) * sin(vt.angle(i)) + x,
+                (vt.value(i) - Real::from(50)) * cos(vt.angle(i)) + y,
+            )
+        });
+    }
+}
+
+/* Inks: body, head, eyes, hand, lollipop */
+pub struct LollipopGuy;
+
+impl Artwork for LollipopGuy {
+    fn name(&self) -> &'static str {
+        "Lollipop Guy"
+    }
+
+    fn draw(&self, plotter: &mut Plotter, palette: &Palette) {
+        let pen = |ink| Stroke::new(PEN_WIDTH, palette.ink(ink));
+
+        //body, open between 6500 and 6900 where the hand comes out
+        let vt = Linear::new(1.0 / (40.0 * PI as f64));
+        let body = |i: u32| {
+            (
+                (vt.value(i) - Real::from(50)) * sin(vt.angle(i)) + Real::from(220),
+                (vt.value(i) + Real::from(50)) * cos(vt.angle(i)) + Real::from(200),
+            )
+        };
+        plotter.set_stroke(pen(0));
+        plotter.curve(0..6500, body);
+        plotter.curve(6901..7000, body);
+
+        //head
+        plotter.set_stroke(pen(1));
+        let vt = Linear::new(1.0 / (60.0 * PI as f64));
+        plotter.curve(0..7000, |i| {
+            (
+                (vt.value(i) + Real::from(50)) * cos(vt.angle(i)) + Real::from(220),
+                (vt.value(i) - Real::from(50)) * sin(vt.angle(i)) + Real::from(60),
+            )
+        });
+
+        //eyes
+        plotter.set_stroke(pen(2));
+        let vt = Linear::new(1.0 / (20.0 * PI as f64));
+        for eye_x in [200, 240] {
+            plotter.curve(0..1300, |i| {
+                let r = vt.value(i) - Real::from(15);
+                (r * sin(vt.angle(i)) + Real::from(eye_x), r * cos(vt.angle(i)) + Real::from(60))
+            });
+        }
+
+        //hand
+        plotter.set_stroke(pen(3));
+        plotter.move_to(Point::new(174, 174));
+        plotter.line_to(Point::new(125, 125));
+
+        //lollipop
+        plotter.set_stroke(pen(4));
+        let vt = Linear::new(1.0 / (30.0 * PI as f64));
+        plotter.curve(0..3300, |i| {
+            let r = vt.value(i) - Real::from(30);
+            (r * sin(vt.angle(i)) + Real::from(110), r * cos(vt.angle(i)) + Real::from(110))
+        });
+    }
+}
+
+/* Rose curve flower (r = size * sin(n * a), angle stepped by d * PI / divisor) on a stem */
+struct Flower {
+    n: f64,
+    d: f64,
+    divisor: f64,
+    points: u32,
+    size: f64,
+    center: Point,
+    stem_top: i32,
+    stem_length: i32,
+}
+
+const FLOWERS: [Flower; 6] = [
+    Flower { n: 6.0, d: 71.0, divisor: 60.0, points: 361, size: 30.0, center: Point::new(35, 180), stem_top: 180, stem_length: 60 },
+    Flower { n: 7.0, d: 19.0, divisor: 300.0, points: 700, size: 30.0, center: Point::new(90, 140), stem_top: 140, stem_length: 100 },
+    Flower { n: 2.0, d: 39.0, divisor: 150.0, points: 500, size: 30.0, center: Point::new(140, 190), stem_top: 190, stem_length: 50 },
+    Flower { n: 8.0, d: 27.0, divisor: 230.0, points: 1000, size: 30.0, center: Point::new(243, 200), stem_top: 200, stem_length: 85 },
+    Flower { n: 5.0, d: 97.0, divisor: 150.0, points: 700, size: 30.0, center: Point::new(290, 155), stem_top: 155, stem_length: 85 },
+    /* The big one, its stem starts below the blossom */
+    Flower { n: 6.0, d: 71.0, divisor: 1200.0, points: 2500, size: 80.0, center: Point::new(200, 90), stem_top: 140, stem_length: 100 },
+];
+
+/* Inks: stems, then one per blossom in `FLOWERS` order */
+pub struct Garden;
+
+impl Artwork for Garden {
+    fn name(&self) -> &'static str {
+        "Garden"
+    }
+
+    fn draw(&self, plotter: &mut Plotter, palette: &Palette) {
+        for (i, flower) in FLOWERS.iter().enumerate() {
+            /* Consecutive samples are far apart, connecting them would draw a different picture */
+            plotter.set_stroke(Stroke::new(BLOSSOM_WIDTH, palette.ink(1 + i)));
+            let step = flower.d * (PI as f64 / flower.divisor);
+            let (a, na) = (Linear::new(step), Linear::new(flower.n * step));
+            let size = real(flower.size);
+            let (x, y) = (Real::from(flower.center.x), Real::from(flower.center.y));
+            plotter.scatter(0..flower.points, |t| {
+                let r = size * sin(na.angle(t));
+                (r * cos(a.angle(t)) + x, r * sin(a.angle(t)) + y)
+            });
+
+            plotter.set_stroke(Stroke::new(PEN_WIDTH, palette.ink(0)));
+            plotter.move_to(Point::new(flower.center.x, flower.stem_top));
+            plotter.line_to(Point::new(flower.center.x, flower.stem_top + flower.stem_length - 1));
+        }
+    }
+}
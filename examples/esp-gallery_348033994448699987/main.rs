@@ -3,24 +3,28 @@
 
 use esp32c3_hal::{
     clock::ClockControl,
-    peripherals::Peripherals,
+    interrupt,
+    peripherals::{self, Peripherals, TIMG0},
     gpio::*,
     prelude::*,
+    riscv,
     spi,
-    timer::TimerGroup,
+    systimer::SystemTimer,
+    timer::{Timer, Timer0, TimerGroup},
     Rtc,
     IO,
     Delay,
 };
 
+use core::cell::{Cell, RefCell};
+use core::sync::atomic::{AtomicBool, Ordering};
+use critical_section::Mutex;
+
 /* Display and graphics */
 use mipidsi::{ Orientation, ColorOrder };
 
 use display_interface_spi::SPIInterfaceNoCS;
 
-use core::f32::consts::PI;
-use libm::{sin, cos};
-
 use embedded_graphics::{
     prelude::RgbColor,
     mono_font::{
@@ -47,59 +51,267 @@ use esp_println::println;
 use esp_backtrace as _;
 
 
-/* Debouncing algorythm */
-pub enum Event {
-    Pressed,
-    Released,
-    Nothing,
+use input::{ButtonState, Encoder, Event, GestureConfig, Rotation, DEFAULT_DEBOUNCE_MS};
+use input::queue::{Edge, EdgeQueue};
+
+mod artwork;
+mod drawings;
+mod menu;
+mod palette;
+mod plot;
+mod sample;
+mod vector;
+use artwork::{Rendering, ANIMATION_INTERVAL_MS, ARTWORKS, ARTWORK_COUNT};
+use plot::View;
+use menu::Menu;
+
+use framebuffer::FrameBuffer;
+
+const MENU_ROW_HEIGHT: u32 = 30;
+
+/* Holding up/down keeps scrolling the menu, holding OK (or pressing BACK) inside an artwork goes back to the menu */
+const SCROLL_GESTURES: GestureConfig = GestureConfig::new().repeat(500, 150);
+const OK_GESTURES: GestureConfig = GestureConfig::new().long_press(800);
+
+/* Indexes of the buttons in `Edge::button` and in the array of button states */
+const UP: usize = 0;
+const DOWN: usize = 1;
+const OK: usize = 2;
+/* Push switch of the rotary encoder, reported as OK */
+const KNOB: usize = 3;
+const BACK: usize = 4;
+
+/* Samples plotted between two looks at the buttons, and the bar showing how far the drawing got */
+const RENDER_STEP_SAMPLES: u32 = 400;
+const PROGRESS_BAR_HEIGHT: u32 = 4;
+const PROGRESS_BAR_COLOR: Rgb565 = Rgb565::BLUE;
+
+/* Slideshow: last menu item, and how long each artwork stays up once it's drawn */
+const SLIDESHOW: usize = ARTWORK_COUNT;
+const SLIDESHOW_HOLD_MS: u64 = 10_000;
+
+/* Screen pixels the view moves per up/down press when panning */
+const PAN_STEP: i32 = 40;
+
+/* What up/down do while an artwork is shown, OK switches to the next one */
+#[derive(Clone, Copy)]
+enum Adjust {
+    Zoom,
+    PanHorizontal,
+    PanVertical,
 }
-pub struct Button<T> {
-    button: T,
-    pressed: bool,
-}
-impl<T: ::embedded_hal::digital::v2::InputPin<Error = core::convert::Infallible>> Button<T> {
-    pub fn new(button: T) -> Self {
-        Button {
-            button,
-            pressed: true,
+
+impl Adjust {
+    fn next(self) -> Self {
+        match self {
+            Adjust::Zoom => Adjust::PanHorizontal,
+            Adjust::PanHorizontal => Adjust::PanVertical,
+            Adjust::PanVertical => Adjust::Zoom,
         }
     }
-    pub fn check(&mut self){
-        self.pressed = !self.button.is_low().unwrap();
+
+    fn name(self) -> &'static str {
+        match self {
+            Adjust::Zoom => "zoom in/out",
+            Adjust::PanHorizontal => "pan left/right",
+            Adjust::PanVertical => "pan up/down",
+        }
     }
 
-    pub fn poll(&mut self, delay :&mut Delay) -> Event {
-        let pressed_now = !self.button.is_low().unwrap();
-        if !self.pressed  &&  pressed_now
-        {
-            delay.delay_ms(30 as u32);
-            self.check();
-            if !self.button.is_low().unwrap() {
-                Event::Pressed
+    /* Up zooms in, pans left or pans up */
+    fn apply(self, view: &mut View, up: bool, screen: Size) {
+        let step = if up { -PAN_STEP } else { PAN_STEP };
+        match self {
+            Adjust::Zoom if up => view.zoom_in(),
+            Adjust::Zoom => view.zoom_out(),
+            Adjust::PanHorizontal => view.pan_by(Point::new(step, 0), screen),
+            Adjust::PanVertical => view.pan_by(Point::new(0, step), screen),
+        }
+        println!("View: zoom {}x, pan {:?}", view.zoom, view.pan);
+    }
+}
+
+/* What a button does while an artwork is shown, the same while it's drawn and once it's complete */
+enum Command {
+    Back,
+    NextAdjust,
+    /* Up (true) or down */
+    Adjust(bool),
+    Nothing,
+}
+
+/* OK acts when it's released, so holding it to go back doesn't switch `Adjust` first.
+ * `ok_down` is set while OK is held, the press that opened the artwork doesn't count */
+fn command(event: Option<(usize, Event)>, ok_down: &mut bool) -> Command {
+    match event {
+        Some((OK, Event::LongPress)) | Some((BACK, Event::Pressed)) => Command::Back,
+        Some((OK, Event::Pressed)) => {
+            *ok_down = true;
+            Command::Nothing
+        }
+        Some((OK, Event::Released)) if core::mem::take(ok_down) => Command::NextAdjust,
+        Some((button @ (UP | DOWN), Event::Pressed | Event::Repeat)) => Command::Adjust(button == UP),
+        _ => Command::Nothing,
+    }
+}
+
+/* Buttons are owned by the GPIO interrupt, which only records edges, the main loop drains them */
+static BUTTON_PINS: Mutex<RefCell<Option<(
+    Gpio0<Input<PullUp>>,
+    Gpio1<Input<PullUp>>,
+    Gpio8<Input<PullUp>>,
+    Gpio19<Input<PullUp>>,
+    Gpio20<Input<PullUp>>,
+)>>> = Mutex::new(RefCell::new(None));
+static EDGES: EdgeQueue<32> = EdgeQueue::new();
+
+/* Rotary encoder CLK (A) and DT (B) lines, decoded right in the interrupt */
+static ENCODER: Mutex<RefCell<Option<(
+    Gpio5<Input<PullUp>>,
+    Gpio18<Input<PullUp>>,
+    Encoder,
+)>>> = Mutex::new(RefCell::new(None));
+/* Whole 320x240 screen (150 KB, fits the C3), black so it's zeroed in .bss instead of flash */
+#[cfg(feature = "full-framebuffer")]
+static mut FRAME: [Rgb565; 320 * 240] = [Rgb565::BLACK; 320 * 240];
+/* Without it artworks go straight to the display and only the menu is buffered, 16 rows at a time (10 KB) */
+#[cfg(not(feature = "full-framebuffer"))]
+static mut MENU_BAND: [Rgb565; 320 * 16] = [Rgb565::BLACK; 320 * 16];
+
+/* Slideshow timer, its interrupt sets `SLIDE_DUE` when the current artwork has been shown long enough */
+static SLIDE_TIMER: Mutex<RefCell<Option<Timer<Timer0<TIMG0>>>>> = Mutex::new(RefCell::new(None));
+static SLIDE_DUE: AtomicBool = AtomicBool::new(false);
+
+/* Detent steps not consumed by the main loop yet, clockwise is positive */
+static ENCODER_STEPS: Mutex<Cell<i32>> = Mutex::new(Cell::new(0));
+
+/* Monotonic time in milliseconds, used to timestamp button edges */
+fn millis() -> u64 {
+    SystemTimer::now() / (SystemTimer::TICKS_PER_SECOND / 1000)
+}
+
+#[interrupt]
+fn GPIO() {
+    let now = millis();
+    critical_section::with(|cs| {
+        if let Some((up, down, ok, knob, back)) = BUTTON_PINS.borrow_ref_mut(cs).as_mut() {
+            if up.is_interrupt_set() {
+                EDGES.push(Edge { button: UP as u8, pressed: up.is_low().unwrap(), timestamp: now });
+                up.clear_interrupt();
+            }
+            if down.is_interrupt_set() {
+                EDGES.push(Edge { button: DOWN as u8, pressed: down.is_low().unwrap(), timestamp: now });
+                down.clear_interrupt();
+            }
+            if ok.is_interrupt_set() {
+                EDGES.push(Edge { button: OK as u8, pressed: ok.is_low().unwrap(), timestamp: now });
+                ok.clear_interrupt();
             }
-            else {
-                Event::Nothing
+            if knob.is_interrupt_set() {
+                EDGES.push(Edge { button: KNOB as u8, pressed: knob.is_low().unwrap(), timestamp: now });
+                knob.clear_interrupt();
+            }
+            if back.is_interrupt_set() {
+                EDGES.push(Edge { button: BACK as u8, pressed: back.is_low().unwrap(), timestamp: now });
+                back.clear_interrupt();
             }
         }
-        else if self.pressed && !pressed_now{
-            delay.delay_ms(30 as u32);
-            self.check();
-            if self.button.is_low().unwrap()
-            {
-                Event::Released
+
+        if let Some((clk, dt, encoder)) = ENCODER.borrow_ref_mut(cs).as_mut() {
+            if clk.is_interrupt_set() || dt.is_interrupt_set() {
+                let steps = ENCODER_STEPS.borrow(cs);
+                match encoder.update(clk.is_high().unwrap(), dt.is_high().unwrap()) {
+                    Some(Rotation::Clockwise) => steps.set(steps.get() + 1),
+                    Some(Rotation::CounterClockwise) => steps.set(steps.get() - 1),
+                    None => {}
+                }
+                clk.clear_interrupt();
+                dt.clear_interrupt();
             }
-            else {
-                Event::Nothing
+        }
+    });
+}
+
+#[interrupt]
+fn TG0_T0_LEVEL() {
+    critical_section::with(|cs| {
+        if let Some(timer) = SLIDE_TIMER.borrow_ref_mut(cs).as_mut() {
+            timer.clear_interrupt();
+        }
+    });
+    SLIDE_DUE.store(true, Ordering::Relaxed);
+}
+
+/* Starts counting the time the current slide is shown */
+fn start_slide_timer(ms: u64) {
+    critical_section::with(|cs| {
+        SLIDE_DUE.store(false, Ordering::Relaxed);
+        if let Some(timer) = SLIDE_TIMER.borrow_ref_mut(cs).as_mut() {
+            timer.start(ms.millis());
+            timer.listen();
+        }
+    });
+}
+
+/* Slideshow is over, the timer neither counts nor interrupts until the next one */
+fn stop_slide_timer() {
+    critical_section::with(|cs| {
+        if let Some(timer) = SLIDE_TIMER.borrow_ref_mut(cs).as_mut() {
+            /* Fails if it isn't counting any more, which is just as good */
+            timer.cancel().ok();
+            timer.unlisten();
+            timer.clear_interrupt();
+        }
+        SLIDE_DUE.store(false, Ordering::Relaxed);
+    });
+}
+
+/* Replays queued edges first (so presses made while drawing aren't lost), then advances timers.
+ * Events a button held back (see `ButtonState`) go before any newer edge.
+ * Turning the knob clockwise is reported as a DOWN press, counter-clockwise as UP */
+fn next_event(buttons: &mut [ButtonState; 5]) -> Option<(usize, Event)> {
+    for (button, state) in buttons.iter_mut().enumerate() {
+        if state.has_pending() {
+            match state.tick(millis()) {
+                Event::Nothing => {}
+                event => return Some((navigation_button(button), event)),
             }
         }
-        else{
-            Event::Nothing
+    }
+
+    while let Some(edge) = EDGES.pop() {
+        let button = edge.button as usize;
+        match buttons[button].update(edge.pressed, edge.timestamp) {
+            Event::Nothing => {}
+            event => return Some((navigation_button(button), event)),
+        }
+    }
+
+    let step = critical_section::with(|cs| {
+        let steps = ENCODER_STEPS.borrow(cs);
+        let step = steps.get().signum();
+        steps.set(steps.get() - step);
+        step
+    });
+    match step {
+        1 => return Some((DOWN, Event::Pressed)),
+        -1 => return Some((UP, Event::Pressed)),
+        _ => {}
+    }
+
+    let now = millis();
+    for (button, state) in buttons.iter_mut().enumerate() {
+        match state.tick(now) {
+            Event::Nothing => {}
+            event => return Some((navigation_button(button), event)),
         }
-        
     }
+    None
 }
 
- 
+fn navigation_button(button: usize) -> usize {
+    if button == KNOB { OK } else { button }
+}
 
 
 #[entry]
@@ -116,6 +328,7 @@ fn main() -> ! {
         &mut system.peripheral_clock_control,
     );
     let mut wdt0 = timer_group0.wdt;
+    let slide_timer = timer_group0.timer0;
     let timer_group1 = TimerGroup::new(
         peripherals.TIMG1,
         &clocks,
@@ -175,462 +388,242 @@ fn main() -> ! {
 
     display.clear(Rgb565::WHITE);
 
-    let eye_plate_tab = display.bounding_box().center() - Size::new(80, 30);
-    let lollipop_plate_tab = display.bounding_box().center() - Size::new(80,0);
-    let garden_plate_tab = display.bounding_box().center() + Size::new(0, 30) - Size::new(80, 0);
-
-    let pointer_offset = Size::new(15, 10);
-
-     
-    let mut button_up = Button::new(io.pins.gpio0.into_pull_up_input());
-    let mut button_down  = Button::new(io.pins.gpio1.into_pull_up_input());
-    let mut button_ok = Button::new(io.pins.gpio8.into_pull_up_input());
-
- 
-    Text::new("Eye",
-            eye_plate_tab,
-            MonoTextStyle::new(&PROFONT_18_POINT, Rgb565::BLACK),
-    )
-    .draw(&mut display)
-    .unwrap();
-
-    Text::new("Lollipop Guy",
-            lollipop_plate_tab, //- Size::new(0, 15), 
-            MonoTextStyle::new(&PROFONT_18_POINT, Rgb565::BLACK),           
-    )
-    .draw(&mut display)
-    .unwrap();
+    /* Everything is drawn into `screen` first, `show` sends what changed to the display */
+    #[cfg(feature = "full-framebuffer")]
+    let mut screen = FrameBuffer::new(
+        unsafe { &mut *core::ptr::addr_of_mut!(FRAME) },
+        display.bounding_box().size,
+        Rgb565::WHITE,
+    );
+    /* Artworks are drawn on the display itself, the menu is rendered band by band in `menu_band` */
+    #[cfg(not(feature = "full-framebuffer"))]
+    let mut screen = display;
+    #[cfg(not(feature = "full-framebuffer"))]
+    let mut menu_band = FrameBuffer::new(
+        unsafe { &mut *core::ptr::addr_of_mut!(MENU_BAND) },
+        screen.bounding_box().size,
+        Rgb565::WHITE,
+    );
 
-    Text::new("Garden",
-            garden_plate_tab, 
-            MonoTextStyle::new(&PROFONT_18_POINT, Rgb565::BLACK),
-    )
-    .draw(&mut display)
-    .unwrap();
+    /* First item where the "Eye" plate used to be, as many rows as fit below it */
+    let menu_origin = screen.bounding_box().center() - Size::new(80, 30);
+    let menu_rows = ((screen.bounding_box().size.height as i32 - menu_origin.y) / MENU_ROW_HEIGHT as i32) as usize;
+    let mut menu_items = ["Slideshow"; ARTWORK_COUNT + 1];
+    for (item, exhibit) in menu_items.iter_mut().zip(ARTWORKS.iter()) {
+        *item = exhibit.artwork.name();
+    }
+    let mut menu = Menu::new(&menu_items, menu_origin, MENU_ROW_HEIGHT, menu_rows);
+
+    /* The progress bar goes straight to the display, over the picture kept in `screen`,
+     * which is sent again once the bar isn't needed any more */
+    let screen_size = screen.bounding_box().size;
+    #[cfg(feature = "full-framebuffer")]
+    let progress_bar = Rectangle::new(
+        Point::new(0, (screen_size.height - PROGRESS_BAR_HEIGHT) as i32),
+        Size::new(screen_size.width, PROGRESS_BAR_HEIGHT),
+    );
+    #[cfg(feature = "full-framebuffer")]
+    let mut progress_shown = false;
+
+    #[cfg(feature = "full-framebuffer")]
+    let mut show = |screen: &mut FrameBuffer<Rgb565>, progress: Option<(u32, u32)>| {
+        if progress.is_none() && progress_shown {
+            screen.invalidate(&progress_bar);
+            progress_shown = false;
+        }
 
-    Circle::new(eye_plate_tab - pointer_offset, 10)
-        .into_styled(
-                    PrimitiveStyleBuilder::new()
-                        .stroke_color(Rgb565::BLACK)
-                        .stroke_width(1)
-                        .fill_color(Rgb565::BLACK)
-                        .build(),
-        )
-        .draw(&mut display)
+        screen.flush(|area, colors| {
+            let end = area.bottom_right().unwrap();
+            display.set_pixels(area.top_left.x as u16, area.top_left.y as u16, end.x as u16, end.y as u16, colors)
+        })
         .unwrap();
 
+        if let Some((done, total)) = progress {
+            let width = progress_bar.size.width * done / total.max(1);
+            display.fill_solid(&Rectangle::new(progress_bar.top_left, Size::new(width, PROGRESS_BAR_HEIGHT)), PROGRESS_BAR_COLOR).unwrap();
+            progress_shown = true;
+        }
+    };
+    /* Nothing is kept to restore the picture under a progress bar from, so there's none */
+    #[cfg(not(feature = "full-framebuffer"))]
+    let show = |_screen: &mut _, _progress: Option<(u32, u32)>| {};
 
-    let mut pointer_position : u8 = 1;
-    let mut last_pointer_position : u8 = 1;
+     
+    let mut button_up = io.pins.gpio0.into_pull_up_input();
+    let mut button_down  = io.pins.gpio1.into_pull_up_input();
+    let mut button_ok = io.pins.gpio8.into_pull_up_input();
+    let mut button_back = io.pins.gpio20.into_pull_up_input();
+
+    button_up.listen(esp32c3_hal::gpio::Event::AnyEdge);
+    button_down.listen(esp32c3_hal::gpio::Event::AnyEdge);
+    button_ok.listen(esp32c3_hal::gpio::Event::AnyEdge);
+    button_back.listen(esp32c3_hal::gpio::Event::AnyEdge);
+
+    /* Rotary encoder: CLK on gpio5, DT on gpio18, push switch on gpio19 */
+    let mut encoder_clk = io.pins.gpio5.into_pull_up_input();
+    let mut encoder_dt = io.pins.gpio18.into_pull_up_input();
+    let mut encoder_sw = io.pins.gpio19.into_pull_up_input();
+
+    encoder_clk.listen(esp32c3_hal::gpio::Event::AnyEdge);
+    encoder_dt.listen(esp32c3_hal::gpio::Event::AnyEdge);
+    encoder_sw.listen(esp32c3_hal::gpio::Event::AnyEdge);
+
+    critical_section::with(|cs| {
+        BUTTON_PINS.borrow_ref_mut(cs).replace((button_up, button_down, button_ok, encoder_sw, button_back));
+        ENCODER.borrow_ref_mut(cs).replace((encoder_clk, encoder_dt, Encoder::new()));
+    });
+
+    critical_section::with(|cs| {
+        SLIDE_TIMER.borrow_ref_mut(cs).replace(slide_timer);
+    });
+
+    interrupt::enable(peripherals::Interrupt::GPIO, interrupt::Priority::Priority2).unwrap();
+    interrupt::enable(peripherals::Interrupt::TG0_T0_LEVEL, interrupt::Priority::Priority1).unwrap();
+    unsafe { riscv::interrupt::enable(); }
+
+    let mut buttons = [
+        ButtonState::new(DEFAULT_DEBOUNCE_MS, SCROLL_GESTURES),
+        ButtonState::new(DEFAULT_DEBOUNCE_MS, SCROLL_GESTURES),
+        ButtonState::new(DEFAULT_DEBOUNCE_MS, OK_GESTURES),
+        ButtonState::new(DEFAULT_DEBOUNCE_MS, OK_GESTURES),
+        ButtonState::new(DEFAULT_DEBOUNCE_MS, GestureConfig::new()),
+    ];
 
     loop {
 
-        if last_pointer_position != pointer_position
-        { 
-            Rectangle::new( match last_pointer_position {
-                                        1 => eye_plate_tab,
-                                        2 => lollipop_plate_tab,
-                                        3 => garden_plate_tab,
-                                        _ => Point::new(0,0), 
-                                    } - Size::new(17,12), Size::new(15, 15))
-            .into_styled(
-                PrimitiveStyleBuilder::new()
-                    .fill_color(Rgb565::WHITE)
-                    .build(),
-            )
-            .draw(&mut display)
-            .unwrap();
-
-            Circle::new(match pointer_position {
-                                        1 => eye_plate_tab,
-                                        2 => lollipop_plate_tab,
-                                        3 => garden_plate_tab,
-                                        _ => Point::new(0,0), 
-                                } - pointer_offset, 10)
-            .into_styled(
-                        PrimitiveStyleBuilder::new()
-                            .stroke_color(Rgb565::BLACK)
-                            .stroke_width(1)
-                            .fill_color(Rgb565::BLACK)
-                            .build(),
-            )
-            .draw(&mut display)
-            .unwrap();
-
-            last_pointer_position = pointer_position
+        #[cfg(feature = "full-framebuffer")]
+        {
+            menu.draw(&mut screen).unwrap();
+            show(&mut screen, None);
+        }
+        /* Each band gets the whole menu, clipped to the rows it holds */
+        #[cfg(not(feature = "full-framebuffer"))]
+        if !menu.is_drawn() {
+            menu_band
+                .render(
+                    |band| {
+                        menu.invalidate();
+                        menu.draw(band).unwrap();
+                    },
+                    |area, colors| {
+                        let end = area.bottom_right().unwrap();
+                        screen.set_pixels(area.top_left.x as u16, area.top_left.y as u16, end.x as u16, end.y as u16, colors)
+                    },
+                )
+                .unwrap();
         }
 
-        if let Event::Pressed = button_up.poll(&mut delay)
+        let input = next_event(&mut buttons);
+
+        if let Some((UP, Event::Pressed | Event::Repeat)) = input
         {
             println!("pressed up");
-            if pointer_position == 1 { pointer_position = 3; }
-            else{ pointer_position -= 1; }
+            menu.previous();
         }
-        if let Event::Pressed = button_down.poll(&mut delay)
+        if let Some((DOWN, Event::Pressed | Event::Repeat)) = input
         {
             println!("pressed down");
-            if pointer_position == 3{ pointer_position = 1; }
-            else{ pointer_position += 1;}
+            menu.next();
         }
 
 
-        if let Event::Pressed = button_ok.poll(&mut delay)
+        /* Every artwork in turn until any button is pressed (or the knob turned) */
+        if let (Some((OK, Event::Pressed)), SLIDESHOW) = (input, menu.selected())
         {
-            display.clear(Rgb565::WHITE);
-            if pointer_position == 1
-            {
-                let default_style = MonoTextStyleBuilder::new()
-                    .font(&FONT_10X20)
-                    .text_color(RgbColor::BLACK)
-                    .build();
-
-                let mut vt;
-                let mut x;
-                let mut y;
-                for i in 0..13200 {
-                    vt = i as f64 / (20.0 * PI as f64);
-                    if i < 8000 {
-                        x = (vt - 50.0) * sin(vt);
-                    } else {
-                        x = (vt + 20.0) * sin(vt);
-                    }
-                    y = (vt - 50.0) * cos(vt);
-                    if i < 8000 {
-                        Text::with_alignment("'", Point::new((x + 160.0) as i32, (y + 125.0) as i32), default_style,  Alignment::Center)
-                            .draw(&mut display)
-                            .unwrap();
-                    } else {
-                        Text::with_alignment("|", Point::new((x + 160.0) as i32, (y + 125.0) as i32), default_style,  Alignment::Center)
-                            .draw(&mut display)
-                            .unwrap();
-                    }
-                }
-                loop
-                {
-                    if let Event::Pressed = button_ok.poll(&mut delay) {break;}
+            println!("Slideshow, press any button to stop");
+            'slideshow: for exhibit in ARTWORKS.iter().cycle() {
+                let artwork = exhibit.artwork;
+                println!("Drawing {}", artwork.name());
+
+                let mut rendering = Rendering::new(*exhibit);
+                while !rendering.step(&mut screen, RENDER_STEP_SAMPLES) {
+                    show(&mut screen, Some(rendering.progress()));
+                    if let Some((_, Event::Pressed)) = next_event(&mut buttons) {break 'slideshow;}
                 }
-                display.clear(Rgb565::WHITE);
-                Text::new("Eye",
-                        eye_plate_tab,
-                        MonoTextStyle::new(&PROFONT_18_POINT, Rgb565::BLACK),
-                )
-                .draw(&mut display)
-                .unwrap();
-
-                Text::new("Lollipop Guy",
-                        lollipop_plate_tab, //- Size::new(0, 15), 
-                        MonoTextStyle::new(&PROFONT_18_POINT, Rgb565::BLACK),           
-                )
-                .draw(&mut display)
-                .unwrap();
-
-                Text::new("Garden",
-                        garden_plate_tab, 
-                        MonoTextStyle::new(&PROFONT_18_POINT, Rgb565::BLACK),
-                )
-                .draw(&mut display)
-                .unwrap();
-
-                Circle::new(eye_plate_tab - pointer_offset, 10)
-                    .into_styled(
-                                PrimitiveStyleBuilder::new()
-                                    .stroke_color(Rgb565::BLACK)
-                                    .stroke_width(1)
-                                    .fill_color(Rgb565::BLACK)
-                                    .build(),
-                    )
-                    .draw(&mut display)
-                    .unwrap();
-
-
-                pointer_position = 1;
-                last_pointer_position = 1;
-            }
-            else if pointer_position == 2
-            {
-                let default_style = MonoTextStyleBuilder::new()
-                    .font(&FONT_10X20)
-                    .text_color(RgbColor::BLACK)
-                    .build();
-
-                let mut vt;
-                let mut x;
-                let mut y;
-
-                //body
-                for i in 0..7000 {
-                    vt = i as f64 / (40.0 * PI as f64);
-                    x = (vt - 50.0) * sin(vt);
-                    y = (vt + 50.0) *  cos(vt);
-                    if i < 6500 || i > 6900 {
-                        Text::with_alignment("'", Point::new((x + 220.0) as i32, (y + 200.0) as i32), default_style,  Alignment::Center)
-                            .draw(&mut display)
-                            .unwrap();
+                show(&mut screen, None);
+
+                /* Held from the moment it's complete, the timer interrupt ends it */
+                start_slide_timer(SLIDESHOW_HOLD_MS);
+                let mut frame = 0;
+                let mut animated = true;
+                let mut next_frame = millis();
+                while !SLIDE_DUE.load(Ordering::Relaxed) {
+                    if let Some((_, Event::Pressed)) = next_event(&mut buttons) {break 'slideshow;}
+
+                    if animated && millis() >= next_frame {
+                        animated = artwork.animate(&mut screen, frame);
+                        show(&mut screen, None);
+                        frame += 1;
+                        next_frame += ANIMATION_INTERVAL_MS;
                     }
-                    
                 }
-
-                //head
-                for i in 0..7000 {
-                    vt = i as f64 / (60.0 * PI as f64);
-                    x = (vt + 50.0) * cos(vt);
-                    y = (vt -  50.0) * sin(vt);
-                    
-                    Text::with_alignment("'", Point::new((x + 220.0) as i32, (y + 60.0) as i32), default_style,  Alignment::Center)
-                        .draw(&mut display)
-                        .unwrap();
-
-                }
-
-                //eyes
-                for i in 0..1300 {
-                    vt = i as f64 / (20.0 * PI as f64);
-                    x = (vt - 15.0) * sin(vt);
-                    y = (vt -  15.0) * cos(vt);
-                    
-                    Text::with_alignment("'", Point::new((x + 200.0) as i32, (y + 60.0) as i32), default_style,  Alignment::Center)
-                        .draw(&mut display)
-                        .unwrap();
-                }
-
-                for i in 0..1300 {
-                    vt = i as f64 / (20.0 * PI as f64);
-                    x = (vt - 15.0) * sin(vt);
-                    y = (vt -  15.0) * cos(vt);
-                    
-                    Text::with_alignment("'", Point::new((x + 240.0) as i32, (y + 60.0) as i32), default_style,  Alignment::Center)
-                        .draw(&mut display)
-                        .unwrap();
-                }
-
-                //hand
-
-                let mut b;
-                for a in (125..175).rev() {
-                    b = a;
-                    Text::with_alignment("-", Point::new(a, b), default_style,  Alignment::Center)
-                        .draw(&mut display)
-                        .unwrap();
-                }
-
-                //lollipop
-
-                for i in 0..3300 {
-                    vt = i as f64 / (30.0 * PI as f64);
-                    x = (vt - 30.0) * sin(vt);
-                    y = (vt -  30.0) * cos(vt);
-                    
-                    Text::with_alignment("'", Point::new((x + 110.0) as i32, (y + 110.0) as i32), default_style,  Alignment::Center)
-                        .draw(&mut display)
-                        .unwrap();
-                }
-
-                loop
-                {
-                    if let Event::Pressed = button_ok.poll(&mut delay) {break;}
-                }
-                display.clear(Rgb565::WHITE);
-                Text::new("Eye",
-                        eye_plate_tab,
-                        MonoTextStyle::new(&PROFONT_18_POINT, Rgb565::BLACK),
-                )
-                .draw(&mut display)
-                .unwrap();
-
-                Text::new("Lollipop Guy",
-                        lollipop_plate_tab, //- Size::new(0, 15), 
-                        MonoTextStyle::new(&PROFONT_18_POINT, Rgb565::BLACK),           
-                )
-                .draw(&mut display)
-                .unwrap();
-
-                Text::new("Garden",
-                        garden_plate_tab, 
-                        MonoTextStyle::new(&PROFONT_18_POINT, Rgb565::BLACK),
-                )
-                .draw(&mut display)
-                .unwrap();
-
-                Circle::new(eye_plate_tab - pointer_offset, 10)
-                    .into_styled(
-                                PrimitiveStyleBuilder::new()
-                                    .stroke_color(Rgb565::BLACK)
-                                    .stroke_width(1)
-                                    .fill_color(Rgb565::BLACK)
-                                    .build(),
-                    )
-                    .draw(&mut display)
-                    .unwrap();
-
-
-                pointer_position = 1;
-                last_pointer_position = 1;
             }
-            else if pointer_position == 3
-            {
-                let default_style = MonoTextStyleBuilder::new()
-                    .font(&FONT_10X20)
-                    .text_color(RgbColor::BLACK)
-                    .build();
-
-                let mut n = 6.0;
-                let mut d = 71.0;    
-                let mut a;
-                let mut r;
-                let mut x;
-                let mut y;
-
-                for t in 0..361 {
-                    a = t as f64 * d * (PI as f64 / 60.0);
-                    r = 30.0 * sin(n * a);
-                    x = r * cos(a);
-                    y = r * sin(a);
-
-                    Text::with_alignment("o", Point::new((x + 35.0) as i32, (y + 180.0) as i32), default_style,  Alignment::Center)
-                        .draw(&mut display)
-                        .unwrap();
-                }
-                let pos_x = 1;
-                for pos_y in 0..60 {
-                    Text::with_alignment("|", Point::new(pos_x + 34, pos_y + 180), default_style,  Alignment::Center)
-                        .draw(&mut display)
-                        .unwrap();
-                }
-
-                n = 7.0;
-                d = 19.0;
-                for t in 0..700 {
-                    a = t as f64 * d * (PI as f64 / 300.0);
-                    r = 30.0 * sin(n * a);
-                    x = r * cos(a);
-                    y = r * sin(a);
-
-                    Text::with_alignment("o", Point::new((x + 90.0) as i32, (y + 140.0) as i32), default_style,  Alignment::Center)
-                        .draw(&mut display)
-                        .unwrap();
-                }
-
-                for pos_y in 0..100 {
-                    Text::with_alignment("|", Point::new(pos_x + 89, pos_y + 140), default_style,  Alignment::Center)
-                        .draw(&mut display)
-                        .unwrap();
-                }
-
-                n = 2.0;
-                d = 39.0;
-                for t in 0..500 {
-                    a = t as f64 * d * (PI as f64 / 150.0);
-                    r = 30.0 * sin(n * a);
-                    x = r * cos(a);
-                    y = r * sin(a);
-
-                    Text::with_alignment("S", Point::new((x + 140.0) as i32, (y + 190.0) as i32), default_style,  Alignment::Center)
-                        .draw(&mut display)
-                        .unwrap();
-                }
-
-                for pos_y in 0..50 {
-                    Text::with_alignment("|", Point::new(pos_x + 139, pos_y + 190), default_style,  Alignment::Center)
-                        .draw(&mut display)
-                        .unwrap();
-                }
-
-                n = 8.0;
-                d = 27.0;
-                for t in 0..1000 {
-                    a = t as f64 * d * (PI as f64 / 230.0);
-                    r = 30.0 * sin(n * a);
-                    x = r * cos(a);
-                    y = r * sin(a);
-
-                    Text::with_alignment("o", Point::new((x + 243.0) as i32, (y + 200.0) as i32), default_style,  Alignment::Center)
-                        .draw(&mut display)
-                        .unwrap();
-                }
-                for pos_y in 0..85 {
-                    Text::with_alignment("|", Point::new(pos_x + 242, pos_y + 200), default_style,  Alignment::Center)
-                        .draw(&mut display)
-                        .unwrap();
-                }
-
-                n = 5.0;
-                d = 97.0;
-                for t in 0..700 {
-                    a = t as f64 * d * (PI as f64 / 150.0);
-                    r = 30.0 * sin(n * a);
-                    x = r * cos(a);
-                    y = r * sin(a);
-
-                    Text::with_alignment("o", Point::new((x + 290.0) as i32, (y + 155.0) as i32), default_style,  Alignment::Center)
-                        .draw(&mut display)
-                        .unwrap();
-                }
-                for pos_y in 0..85 {
-                    Text::with_alignment("|", Point::new(pos_x + 289, pos_y + 155), default_style,  Alignment::Center)
-                        .draw(&mut display)
-                        .unwrap();
-                }
-
-                n = 6.0;
-                d = 71.0;
-                for t in 0..2500 {
-                    a = t as f64 * d * (PI as f64 / 1200.0);
-                    r = 80.0 * sin(n * a);
-                    x = r * cos(a);
-                    y = r * sin(a);
-
-                    Text::with_alignment("o", Point::new((x + 200.0) as i32, (y + 90.0) as i32), default_style,  Alignment::Center)
-                        .draw(&mut display)
-                        .unwrap();
-                }
+            stop_slide_timer();
+            screen.clear(Rgb565::WHITE).unwrap();
+            menu.invalidate();
+            continue;
+        }
 
-                for pos_y in 0..100 {
-                    Text::with_alignment("|", Point::new(pos_x + 199, pos_y + 140), default_style,  Alignment::Center)
-                        .draw(&mut display)
-                        .unwrap();
+        if let Some((OK, Event::Pressed)) = input
+        {
+            let exhibit = ARTWORKS[menu.selected()];
+            let artwork = exhibit.artwork;
+
+            /* Drawn a piece at a time on the palette's background, holding OK or pressing BACK stops it and goes
+             * back to the menu. Up/down change the view (see `Adjust`), which starts the drawing over */
+            println!("Drawing {}, hold OK or press BACK to cancel", artwork.name());
+            let mut view = View::WHOLE;
+            let mut adjust = Adjust::Zoom;
+            let mut ok_down = false;
+            let mut rendering = Rendering::new(exhibit);
+            'shown: loop {
+                while !rendering.step(&mut screen, RENDER_STEP_SAMPLES) {
+                    show(&mut screen, Some(rendering.progress()));
+                    match command(next_event(&mut buttons), &mut ok_down) {
+                        Command::Back => break 'shown,
+                        Command::NextAdjust => {
+                            adjust = adjust.next();
+                            println!("Up/down: {}", adjust.name());
+                        }
+                        Command::Adjust(up) => {
+                            adjust.apply(&mut view, up, screen_size);
+                            rendering = Rendering::new(exhibit).with_view(view);
+                        }
+                        Command::Nothing => {}
+                    }
                 }
+                show(&mut screen, None);
+
+                println!("Up/down: {}, OK: change that, hold OK or press BACK to return to the menu", adjust.name());
+                let mut frame = 0;
+                /* Animations draw at the artwork's own coordinates, so only on the whole view */
+                let mut animated = view == View::WHOLE;
+                let mut next_frame = millis();
+                loop {
+                    match command(next_event(&mut buttons), &mut ok_down) {
+                        Command::Back => break 'shown,
+                        Command::NextAdjust => {
+                            adjust = adjust.next();
+                            println!("Up/down: {}", adjust.name());
+                        }
+                        Command::Adjust(up) => {
+                            adjust.apply(&mut view, up, screen_size);
+                            rendering = Rendering::new(exhibit).with_view(view);
+                            continue 'shown;
+                        }
+                        Command::Nothing => {}
+                    }
 
-                loop
-                {
-                    if let Event::Pressed = button_ok.poll(&mut delay) {break;}
+                    if animated && millis() >= next_frame {
+                        animated = artwork.animate(&mut screen, frame);
+                        show(&mut screen, None);
+                        frame += 1;
+                        next_frame += ANIMATION_INTERVAL_MS;
+                    }
                 }
-                display.clear(Rgb565::WHITE);
-                Text::new("Eye",
-                        eye_plate_tab,
-                        MonoTextStyle::new(&PROFONT_18_POINT, Rgb565::BLACK),
-                )
-                .draw(&mut display)
-                .unwrap();
-
-                Text::new("Lollipop Guy",
-                        lollipop_plate_tab, //- Size::new(0, 15), 
-                        MonoTextStyle::new(&PROFONT_18_POINT, Rgb565::BLACK),           
-                )
-                .draw(&mut display)
-                .unwrap();
-
-                Text::new("Garden",
-                        garden_plate_tab, 
-                        MonoTextStyle::new(&PROFONT_18_POINT, Rgb565::BLACK),
-                )
-                .draw(&mut display)
-                .unwrap();
-
-                Circle::new(eye_plate_tab - pointer_offset, 10)
-                    .into_styled(
-                                PrimitiveStyleBuilder::new()
-                                    .stroke_color(Rgb565::BLACK)
-                                    .stroke_width(1)
-                                    .fill_color(Rgb565::BLACK)
-                                    .build(),
-                    )
-                    .draw(&mut display)
-                    .unwrap();
-
-
-                pointer_position = 1;
-                last_pointer_position = 1;
             }
+            screen.clear(Rgb565::WHITE).unwrap();
+            menu.invalidate();
         }
     }
 }
\ No newline at end of file
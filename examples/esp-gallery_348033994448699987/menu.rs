@@ -0,0 +1,147 @@
+use embedded_graphics::{
+    mono_font::MonoTextStyle,
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{Circle, PrimitiveStyle, Rectangle},
+    text::Text,
+};
+
+use profont::PROFONT_18_POINT;
+
+/* Offset of the cursor (filled circle) from the start of the item text */
+const CURSOR_OFFSET: Size = Size::new(15, 10);
+const CURSOR_DIAMETER: u32 = 10;
+/* Area cleared around the cursor and in front of/above an item text */
+const CURSOR_CLEAR: Size = Size::new(17, 12);
+const TEXT_ASCENT: i32 = 20;
+
+/* Vertical list of items with a cursor in front of the selected one.
+ *
+ * Selection wraps around, if there are more items than `visible_rows` the list scrolls
+ * to keep the selected item on screen. `draw` only redraws what changed: the cursor when
+ * the selection moved, all visible rows when the list scrolled (or after `invalidate`). */
+pub struct Menu<'a> {
+    items: &'a [&'a str],
+    origin: Point,
+    row_height: u32,
+    visible_rows: usize,
+    selected: usize,
+    top: usize,
+    drawn: Option<(usize, usize)>,
+}
+
+impl<'a> Menu<'a> {
+    /* `origin` is the start of the first item text (left, baseline) */
+    pub fn new(items: &'a [&'a str], origin: Point, row_height: u32, visible_rows: usize) -> Self {
+        Menu {
+            items,
+            origin,
+            row_height,
+            visible_rows: visible_rows.max(1),
+            selected: 0,
+            top: 0,
+            drawn: None,
+        }
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn select(&mut self, index: usize) {
+        if index < self.items.len() {
+            self.selected = index;
+            self.scroll_to_selected();
+        }
+    }
+
+    pub fn next(&mut self) {
+        if !self.items.is_empty() {
+            self.select((self.selected + 1) % self.items.len());
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if !self.items.is_empty() {
+            self.select((self.selected + self.items.len() - 1) % self.items.len());
+        }
+    }
+
+    /* Screen was drawn over, next `draw` repaints the whole menu */
+    pub fn invalidate(&mut self) {
+        self.drawn = None;
+    }
+
+    /* Nothing changed since the last `draw` */
+    pub fn is_drawn(&self) -> bool {
+        self.drawn == Some((self.top, self.selected))
+    }
+
+    pub fn draw<D>(&mut self, display: &mut D) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        match self.drawn {
+            Some((top, selected)) if top == self.top => {
+                if selected != self.selected {
+                    self.clear_cursor(display, selected - top)?;
+                    self.draw_cursor(display, self.selected - self.top)?;
+                }
+            }
+            _ => {
+                for row in 0..self.visible_rows {
+                    self.clear_row(display, row)?;
+                    if let Some(item) = self.items.get(self.top + row) {
+                        Text::new(item, self.row_origin(row), MonoTextStyle::new(&PROFONT_18_POINT, Rgb565::BLACK))
+                            .draw(display)?;
+                    }
+                }
+                self.draw_cursor(display, self.selected - self.top)?;
+            }
+        }
+
+        self.drawn = Some((self.top, self.selected));
+        Ok(())
+    }
+
+    fn scroll_to_selected(&mut self) {
+        if self.selected < self.top {
+            self.top = self.selected;
+        } else if self.selected >= self.top + self.visible_rows {
+            self.top = self.selected + 1 - self.visible_rows;
+        }
+    }
+
+    fn row_origin(&self, row: usize) -> Point {
+        self.origin + Size::new(0, self.row_height * row as u32)
+    }
+
+    fn clear_row<D>(&self, display: &mut D, row: usize) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        let top_left = self.row_origin(row) - Point::new(CURSOR_CLEAR.width as i32, TEXT_ASCENT);
+        let width = display.bounding_box().size.width.saturating_sub(top_left.x.max(0) as u32);
+        Rectangle::new(top_left, Size::new(width, self.row_height))
+            .into_styled(PrimitiveStyle::with_fill(Rgb565::WHITE))
+            .draw(display)
+    }
+
+    fn clear_cursor<D>(&self, display: &mut D, row: usize) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        Rectangle::new(self.row_origin(row) - CURSOR_CLEAR, Size::new(15, 15))
+            .into_styled(PrimitiveStyle::with_fill(Rgb565::WHITE))
+            .draw(display)
+    }
+
+    fn draw_cursor<D>(&self, display: &mut D, row: usize) -> Result<(), D::Error>
+    where
+        D: DrawTarget<Color = Rgb565>,
+    {
+        Circle::new(self.row_origin(row) - CURSOR_OFFSET, CURSOR_DIAMETER)
+            .into_styled(PrimitiveStyle::with_fill(Rgb565::BLACK))
+            .draw(display)
+    }
+}
@@ -0,0 +1,52 @@
+/* Colors of an artwork.
+ *
+ * A palette is a background and a list of inks. Drawings pick their inks by index (what each
+ * index is for is documented on the drawing), so the same drawing can be shown with another
+ * palette by changing its line in the registry (`DRAWINGS` in artwork.rs). An ink is a
+ * solid color or a gradient, which runs from start to end along each curve drawn with it. */
+
+use embedded_graphics::{pixelcolor::Rgb565, prelude::*};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Ink {
+    Solid(Rgb565),
+    Gradient(Rgb565, Rgb565),
+}
+
+impl Ink {
+    /* Color at sample `position` of a curve with `length` samples */
+    pub fn at(&self, position: u32, length: u32) -> Rgb565 {
+        match *self {
+            Ink::Solid(color) => color,
+            Ink::Gradient(from, to) => {
+                let last = length.saturating_sub(1).max(1) as i32;
+                let position = position.min(last as u32) as i32;
+                let mix = |a: u8, b: u8| (a as i32 + (b as i32 - a as i32) * position / last) as u8;
+                Rgb565::new(mix(from.r(), to.r()), mix(from.g(), to.g()), mix(from.b(), to.b()))
+            }
+        }
+    }
+}
+
+pub struct Palette {
+    pub background: Rgb565,
+    pub inks: &'static [Ink],
+}
+
+impl Palette {
+    /* Ink number `index`, repeating from the start when the palette has fewer */
+    pub fn ink(&self, index: usize) -> Ink {
+        if self.inks.is_empty() {
+            Ink::Solid(Rgb565::BLACK)
+        } else {
+            self.inks[index % self.inks.len()]
+        }
+    }
+}
+
+/* Black on white, how the gallery looked before it had colors. The SVG artworks bring their
+ * own stroke colors and only take the background from it */
+pub const PAPER: Palette = Palette {
+    background: Rgb565::WHITE,
+    inks: &[Ink::Solid(Rgb565::BLACK)],
+};
@@ -0,0 +1,292 @@
+/* Rasterises curves for the artworks.
+ *
+ * A curve is sampled at integer steps and either connected with line segments (`curve`) or
+ * plotted point by point (`scatter`, for curves whose consecutive samples lie far apart,
+ * like the Maurer roses in the garden). Samples landing on the same pixel as the previous
+ * one are skipped. Segments are drawn with a square brush of the stroke width, merged into
+ * horizontal and vertical runs, so every run is one `fill_solid` (one display window)
+ * instead of a text glyph per point.
+ *
+ * Every point plotted is a sample, counted over the whole drawing. A plotter can be limited
+ * to a window of samples, which is how artworks get drawn a piece at a time.
+ *
+ * Points go through a `View` on their way to the canvas, so the curves are sampled again at
+ * the magnification they are shown at instead of scaling up pixels.
+ *
+ * The stroke's ink is picked per sample, a gradient goes from the first sample of a curve to
+ * its last (a single `line_to` or `dot` gets the start color).
+ *
+ * Samples are `Real`s (see `sample`), from the curve function through the view to the pixel. */
+
+use core::ops::Range;
+
+use embedded_graphics::{
+    pixelcolor::Rgb565,
+    prelude::*,
+    primitives::{Line, Rectangle},
+};
+
+use crate::artwork::Canvas;
+use crate::palette::Ink;
+use crate::sample::{real, to_i32, Real};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stroke {
+    pub width: u32,
+    pub ink: Ink,
+}
+
+impl Stroke {
+    pub const fn new(width: u32, ink: Ink) -> Self {
+        Stroke { width, ink }
+    }
+}
+
+/* Part of the drawing that is shown: the drawing is moved by `pan` (drawing pixels, positive
+ * moves the view right/down) and magnified `zoom` times around the middle of the canvas.
+ * The stroke width stays the same, zooming in thins the lines out */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct View {
+    pub zoom: f64,
+    pub pan: (f64, f64),
+}
+
+impl View {
+    pub const WHOLE: View = View { zoom: 1.0, pan: (0.0, 0.0) };
+    pub const MAX_ZOOM: f64 = 16.0;
+
+    pub fn zoom_in(&mut self) {
+        self.zoom = (self.zoom * 2.0).min(Self::MAX_ZOOM);
+    }
+
+    /* Back at 1x the whole drawing is shown again */
+    pub fn zoom_out(&mut self) {
+        self.zoom = (self.zoom / 2.0).max(1.0);
+        if self.zoom == 1.0 {
+            self.pan = (0.0, 0.0);
+        }
+    }
+
+    /* Moves the view `distance` screen pixels (the same on screen whatever the zoom), the
+     * middle of the screen stays on the drawing (`size`) */
+    pub fn pan_by(&mut self, distance: Point, size: Size) {
+        let (width, height) = (size.width as f64 / 2.0, size.height as f64 / 2.0);
+        self.pan.0 = (self.pan.0 + distance.x as f64 / self.zoom).clamp(-width, width);
+        self.pan.1 = (self.pan.1 + distance.y as f64 / self.zoom).clamp(-height, height);
+    }
+
+    fn transform(&self, center: Point) -> Transform {
+        Transform {
+            zoom: real(self.zoom),
+            pan: (real(self.pan.0), real(self.pan.1)),
+            center: (Real::from(center.x), Real::from(center.y)),
+        }
+    }
+}
+
+/* `View` in the numbers of the samples, converted once per plotter */
+#[derive(Clone, Copy)]
+struct Transform {
+    zoom: Real,
+    pan: (Real, Real),
+    center: (Real, Real),
+}
+
+impl Transform {
+    fn apply(&self, (x, y): (Real, Real)) -> Point {
+        let (cx, cy) = self.center;
+        Point::new(
+            to_i32((x - cx - self.pan.0) * self.zoom + cx),
+            to_i32((y - cy - self.pan.1) * self.zoom + cy),
+        )
+    }
+}
+
+pub struct Plotter<'a> {
+    canvas: &'a mut dyn Canvas,
+    stroke: Stroke,
+    /* Color of the stroke's ink at the current sample */
+    color: Rgb565,
+    /* Pen position, `None` when the pen is up */
+    last: Option<Point>,
+    /* Only samples with an index in `window` are drawn */
+    window: Range<u32>,
+    samples: u32,
+    transform: Transform,
+    bounds: Rectangle,
+}
+
+impl<'a> Plotter<'a> {
+    pub fn new(canvas: &'a mut dyn Canvas, stroke: Stroke) -> Self {
+        let bounds = canvas.bounds();
+        Plotter {
+            canvas,
+            stroke,
+            color: stroke.ink.at(0, 1),
+            last: None,
+            window: 0..u32::MAX,
+            samples: 0,
+            transform: View::WHOLE.transform(bounds.center()),
+            bounds,
+        }
+    }
+
+    pub fn with_view(mut self, view: View) -> Self {
+        self.transform = view.transform(self.bounds.center());
+        self
+    }
+
+    /* Draws only the samples in `window`, counted over the whole drawing. Running the same
+     * drawing with consecutive windows draws it piece by piece; skipped samples are just
+     * counted, the curve functions aren't evaluated for them */
+    pub fn with_window(mut self, window: Range<u32>) -> Self {
+        self.window = window;
+        self
+    }
+
+    /* Samples the drawing had so far, drawn or skipped */
+    pub fn samples(&self) -> u32 {
+        self.samples
+    }
+
+    pub fn set_stroke(&mut self, stroke: Stroke) {
+        self.stroke = stroke;
+        self.color = stroke.ink.at(0, 1);
+    }
+
+    /* Puts the pen at `point` without drawing, a following `line_to` starts there */
+    pub fn move_to(&mut self, point: Point) {
+        self.last = Some(self.to_screen(point));
+    }
+
+    pub fn line_to(&mut self, point: Point) {
+        let point = self.to_screen(point);
+        self.color = self.stroke.ink.at(0, 1);
+        if self.claim(1).is_empty() {
+            self.last = Some(point);
+        } else {
+            self.pen_to(point);
+        }
+    }
+
+    /* Square of the stroke width centered on `point` */
+    pub fn dot(&mut self, point: Point) {
+        let point = self.to_screen(point);
+        self.color = self.stroke.ink.at(0, 1);
+        if !self.claim(1).is_empty() {
+            self.stamp(point);
+        }
+    }
+
+    /* Polyline through `f(t)` for every `t` in `steps`, pen up before the first point */
+    pub fn curve(&mut self, steps: Range<u32>, f: impl Fn(u32) -> (Real, Real)) {
+        let drawn = self.claim(steps.len() as u32);
+        self.last = None;
+        if drawn.is_empty() {
+            return;
+        }
+
+        /* Resuming in the middle of the curve, continue from where the last window ended */
+        if drawn.start > 0 {
+            self.last = Some(self.to_point(f(steps.start + drawn.start - 1)));
+        }
+        for t in steps.start + drawn.start..steps.start + drawn.end {
+            self.color = self.stroke.ink.at(t - steps.start, steps.len() as u32);
+            self.pen_to(self.to_point(f(t)));
+        }
+    }
+
+    /* A dot at `f(t)` for every `t` in `steps` */
+    pub fn scatter(&mut self, steps: Range<u32>, f: impl Fn(u32) -> (Real, Real)) {
+        let drawn = self.claim(steps.len() as u32);
+        self.last = None;
+        for t in steps.start + drawn.start..steps.start + drawn.end {
+            let point = self.to_point(f(t));
+            if self.last != Some(point) {
+                self.color = self.stroke.ink.at(t - steps.start, steps.len() as u32);
+                self.stamp(point);
+                self.last = Some(point);
+            }
+        }
+    }
+
+    /* Sample of a curve function on the canvas */
+    fn to_point(&self, sample: (Real, Real)) -> Point {
+        self.transform.apply(sample)
+    }
+
+    fn to_screen(&self, point: Point) -> Point {
+        self.to_point((Real::from(point.x), Real::from(point.y)))
+    }
+
+    /* Counts `count` samples, returns the ones (0..count) inside the window */
+    fn claim(&mut self, count: u32) -> Range<u32> {
+        let first = self.samples;
+        self.samples = self.samples.saturating_add(count);
+        let start = self.window.start.clamp(first, self.samples) - first;
+        let end = self.window.end.clamp(first, self.samples) - first;
+        start..end.max(start)
+    }
+
+    fn pen_to(&mut self, point: Point) {
+        match self.last {
+            Some(last) if last == point => return,
+            Some(last) => self.segment(last, point),
+            None => self.stamp(point),
+        }
+        self.last = Some(point);
+    }
+
+    /* Square brush of the stroke width moved along the line */
+    fn segment(&mut self, from: Point, to: Point) {
+        /* Zoomed in most of the drawing is off the canvas, don't walk it pixel by pixel */
+        let reach = self.stroke.width as i32;
+        let (low, high) = (from.component_min(to), from.component_max(to));
+        let canvas_end = self.bounds.bottom_right().unwrap();
+        if high.x + reach < self.bounds.top_left.x
+            || high.y + reach < self.bounds.top_left.y
+            || low.x - reach > canvas_end.x
+            || low.y - reach > canvas_end.y
+        {
+            return;
+        }
+
+        let mut run: Option<(Point, Point)> = None;
+        for point in Line::new(from, to).points() {
+            run = match run {
+                Some((start, end)) if continues_run(start, end, point) => Some((start, point)),
+                _ => {
+                    self.flush_run(run);
+                    Some((point, point))
+                }
+            };
+        }
+        self.flush_run(run);
+    }
+
+    fn stamp(&mut self, point: Point) {
+        let area = Rectangle::with_center(point, Size::new_equal(self.stroke.width.max(1)));
+        self.canvas.fill_solid(&area, self.color);
+    }
+
+    fn flush_run(&mut self, run: Option<(Point, Point)>) {
+        if let Some((start, end)) = run {
+            let brush = Size::new_equal(self.stroke.width.max(1));
+            let (first, last) = (Rectangle::with_center(start, brush), Rectangle::with_center(end, brush));
+            let area = Rectangle::with_corners(
+                first.top_left.component_min(last.top_left),
+                first.bottom_right().unwrap().component_max(last.bottom_right().unwrap()),
+            );
+            self.canvas.fill_solid(&area, self.color);
+        }
+    }
+}
+
+/* Next pixel of a horizontal or vertical run, in the direction the run already goes */
+fn continues_run(start: Point, end: Point, point: Point) -> bool {
+    let step = point - end;
+    if step.x.abs() + step.y.abs() != 1 {
+        return false;
+    }
+    start == end || (end - start).x.signum() == step.x && (end - start).y.signum() == step.y
+}
@@ -0,0 +1,96 @@
+/* Arithmetic of the curve functions, evaluated for every sample.
+ *
+ * By default `Real` is an f64 and an `Angle` is in radians, with sin/cos from libm. With the
+ * `fixed-trig` feature `Real` is Q16.16 and angles are binary angles (2^32 a full turn) from
+ * the `trig` crate, so sampling a curve needs no floating point, which the C3 only has in
+ * software. The f64 constants of a curve are converted once, before it's sampled: with
+ * `real` or into a `Linear` the curve takes its angles and parameters from. */
+
+#[cfg(not(feature = "fixed-trig"))]
+mod float {
+    pub use libm::{cos, sin};
+
+    pub type Real = f64;
+    pub type Angle = f64;
+
+    pub fn real(value: f64) -> Real {
+        value
+    }
+
+    pub fn to_i32(value: Real) -> i32 {
+        value as i32
+    }
+
+    /* `t * step` for the sample index `t`, as a number and as an angle */
+    #[derive(Clone, Copy)]
+    pub struct Linear {
+        step: f64,
+    }
+
+    impl Linear {
+        pub fn new(step: f64) -> Self {
+            Linear { step }
+        }
+
+        pub fn value(&self, t: u32) -> Real {
+            t as f64 * self.step
+        }
+
+        pub fn angle(&self, t: u32) -> Angle {
+            t as f64 * self.step
+        }
+    }
+}
+
+#[cfg(not(feature = "fixed-trig"))]
+pub use float::*;
+
+#[cfg(feature = "fixed-trig")]
+mod fixed {
+    pub use trig::{Angle, Fixed as Real};
+
+    pub fn sin(angle: Angle) -> Real {
+        Real::sin(angle)
+    }
+
+    pub fn cos(angle: Angle) -> Real {
+        Real::cos(angle)
+    }
+
+    pub fn real(value: f64) -> Real {
+        Real::from_f64(value)
+    }
+
+    pub fn to_i32(value: Real) -> i32 {
+        value.to_i32()
+    }
+
+    /* `t * step` for the sample index `t`, as a number and as an angle. The step is kept in
+     * Q32.32, and as an angle multiplied by `t` with wrapping, so full turns drop out exactly
+     * and neither drifts over thousands of samples */
+    #[derive(Clone, Copy)]
+    pub struct Linear {
+        step: i64,
+        angle: Angle,
+    }
+
+    impl Linear {
+        pub fn new(step: f64) -> Self {
+            Linear {
+                step: (step * 4294967296.0) as i64,
+                angle: trig::from_radians(step),
+            }
+        }
+
+        pub fn value(&self, t: u32) -> Real {
+            Real(((t as i64 * self.step) >> 16) as i32)
+        }
+
+        pub fn angle(&self, t: u32) -> Angle {
+            self.angle.wrapping_mul(t)
+        }
+    }
+}
+
+#[cfg(feature = "fixed-trig")]
+pub use fixed::*;
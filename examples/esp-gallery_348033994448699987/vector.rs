@@ -0,0 +1,77 @@
+/* Artworks made from the SVG files in assets/, converted to command tables by build.rs */
+
+use core::f64::consts::PI;
+
+use embedded_graphics::{pixelcolor::Rgb565, prelude::Point};
+
+use crate::artwork::Artwork;
+use crate::palette::{Ink, Palette};
+use crate::plot::{Plotter, Stroke};
+use crate::sample::{cos, sin, Angle, Linear, Real};
+
+/* Points of a plain rose curve, Maurer roses always have 361 (one per `d` step) */
+const ROSE_SAMPLES: u32 = 720;
+const MAURER_POINTS: u32 = 361;
+
+/* Coordinates are screen pixels */
+#[derive(Clone, Copy, Debug)]
+pub enum Command {
+    /* Stroke width and color for everything that follows */
+    Stroke(u32, Rgb565),
+    MoveTo(i16, i16),
+    LineTo(i16, i16),
+    Circle { center: (i16, i16), radius: u16 },
+    /* r = radius * sin(n * angle), dots every `d` degrees if given (Maurer rose) */
+    Rose { center: (i16, i16), radius: u16, n: f32, d: Option<f32> },
+}
+
+pub struct VectorArt {
+    pub name: &'static str,
+    pub commands: &'static [Command],
+}
+
+impl Artwork for VectorArt {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /* Colors come with the commands, the palette only gives the background */
+    fn draw(&self, plotter: &mut Plotter, _palette: &Palette) {
+        for command in self.commands {
+            match *command {
+                Command::Stroke(width, color) => plotter.set_stroke(Stroke::new(width, Ink::Solid(color))),
+                Command::MoveTo(x, y) => plotter.move_to(Point::new(x.into(), y.into())),
+                Command::LineTo(x, y) => plotter.line_to(Point::new(x.into(), y.into())),
+                Command::Circle { center, radius } => {
+                    /* Segments about 3 px long */
+                    let segments = (radius as u32 * 2).max(12);
+                    let angle = Linear::new(2.0 * PI / segments as f64);
+                    let radius = Real::from(radius as i32);
+                    plotter.curve(0..segments + 1, |t| polar(center, radius, angle.angle(t)));
+                }
+                Command::Rose { center, radius, n, d: None } => {
+                    let step = 2.0 * PI / ROSE_SAMPLES as f64;
+                    let (angle, n_angle) = (Linear::new(step), Linear::new(n as f64 * step));
+                    let radius = Real::from(radius as i32);
+                    plotter.curve(0..ROSE_SAMPLES + 1, |t| {
+                        polar(center, radius * sin(n_angle.angle(t)), angle.angle(t))
+                    });
+                }
+                Command::Rose { center, radius, n, d: Some(d) } => {
+                    let step = (d as f64).to_radians();
+                    let (angle, n_angle) = (Linear::new(step), Linear::new(n as f64 * step));
+                    let radius = Real::from(radius as i32);
+                    plotter.scatter(0..MAURER_POINTS, |t| {
+                        polar(center, radius * sin(n_angle.angle(t)), angle.angle(t))
+                    });
+                }
+            }
+        }
+    }
+}
+
+fn polar(center: (i16, i16), r: Real, angle: Angle) -> (Real, Real) {
+    (Real::from(center.0 as i32) + r * cos(angle), Real::from(center.1 as i32) + r * sin(angle))
+}
+
+include!(concat!(env!("OUT_DIR"), "/vector_artworks.rs"));
@@ -0,0 +1,166 @@
+/* Things happening in response to keypad input: opening the lock, beeping, ...
+ *
+ * Every action is triggered by the main loop and polled on each pass with a millisecond
+ * timestamp, so timed behaviour (re-locking, beep patterns) never blocks keypad scanning.
+ * New actions only have to implement `Action`. */
+
+use embedded_hal::digital::v2::OutputPin;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trigger {
+    KeyPress,
+    /* Correct code, the door may open */
+    Granted,
+    /* Wrong code, or input while locked out */
+    Denied,
+    /* Admin command accepted, nothing should open */
+    Confirm,
+}
+
+pub trait Action {
+    fn trigger(&mut self, trigger: Trigger, now: u64);
+
+    fn poll(&mut self, now: u64);
+}
+
+pub fn trigger_all(actions: &mut [&mut dyn Action], trigger: Trigger, now: u64) {
+    for action in actions.iter_mut() {
+        action.trigger(trigger, now);
+    }
+}
+
+pub fn poll_all(actions: &mut [&mut dyn Action], now: u64) {
+    for action in actions.iter_mut() {
+        action.poll(now);
+    }
+}
+
+/* Relay or solenoid driven high while unlocked, re-locks by itself after `unlock_ms` */
+pub struct DoorLock<P> {
+    pin: P,
+    unlock_ms: u64,
+    relock_at: Option<u64>,
+}
+
+impl<P: OutputPin<Error = core::convert::Infallible>> DoorLock<P> {
+    pub fn new(mut pin: P, unlock_ms: u64) -> Self {
+        pin.set_low().unwrap();
+        DoorLock {
+            pin,
+            unlock_ms,
+            relock_at: None,
+        }
+    }
+}
+
+impl<P: OutputPin<Error = core::convert::Infallible>> Action for DoorLock<P> {
+    fn trigger(&mut self, trigger: Trigger, now: u64) {
+        if trigger == Trigger::Granted {
+            self.pin.set_high().unwrap();
+            self.relock_at = Some(now + self.unlock_ms);
+        }
+    }
+
+    fn poll(&mut self, now: u64) {
+        if let Some(relock_at) = self.relock_at {
+            if now >= relock_at {
+                self.pin.set_low().unwrap();
+                self.relock_at = None;
+            }
+        }
+    }
+}
+
+/* Pitches the buzzer can play, the beeper maps them to frequencies */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Tone {
+    Low,
+    Mid,
+    High,
+}
+
+/* Plays a tone (e.g. LEDC channel on a timer of that frequency, duty 50 %) or stays silent */
+pub trait Beeper {
+    fn set(&mut self, tone: Option<Tone>);
+}
+
+/* Beep pattern as (tone, on, off), durations in milliseconds */
+pub type Pattern = &'static [(Tone, u16, u16)];
+
+pub const KEYPRESS_PATTERN: Pattern = &[(Tone::High, 30, 0)];
+/* Rising, the door opens */
+pub const GRANTED_PATTERN: Pattern = &[(Tone::Mid, 80, 60), (Tone::Mid, 80, 60), (Tone::High, 300, 0)];
+pub const DENIED_PATTERN: Pattern = &[(Tone::Low, 700, 0)];
+pub const CONFIRM_PATTERN: Pattern = &[(Tone::Mid, 80, 60), (Tone::Mid, 80, 0)];
+
+/* Plays a distinct tone and beep pattern for every trigger, a new trigger interrupts the running one */
+pub struct Buzzer<B> {
+    beeper: B,
+    pattern: Pattern,
+    step: usize,
+    on: bool,
+    next_change: Option<u64>,
+}
+
+impl<B: Beeper> Buzzer<B> {
+    pub fn new(mut beeper: B) -> Self {
+        beeper.set(None);
+        Buzzer {
+            beeper,
+            pattern: &[],
+            step: 0,
+            on: false,
+            next_change: None,
+        }
+    }
+
+    pub fn play(&mut self, pattern: Pattern, now: u64) {
+        self.pattern = pattern;
+        self.step = 0;
+        self.start_step(now);
+    }
+
+    fn start_step(&mut self, now: u64) {
+        match self.pattern.get(self.step) {
+            Some((tone, on_ms, _)) => {
+                self.beeper.set(Some(*tone));
+                self.on = true;
+                self.next_change = Some(now + *on_ms as u64);
+            }
+            None => {
+                self.beeper.set(None);
+                self.on = false;
+                self.next_change = None;
+            }
+        }
+    }
+}
+
+impl<B: Beeper> Action for Buzzer<B> {
+    fn trigger(&mut self, trigger: Trigger, now: u64) {
+        let pattern = match trigger {
+            Trigger::KeyPress => KEYPRESS_PATTERN,
+            Trigger::Granted => GRANTED_PATTERN,
+            Trigger::Denied => DENIED_PATTERN,
+            Trigger::Confirm => CONFIRM_PATTERN,
+        };
+        self.play(pattern, now);
+    }
+
+    fn poll(&mut self, now: u64) {
+        let next_change = match self.next_change {
+            Some(next_change) if now >= next_change => next_change,
+            _ => return,
+        };
+
+        if self.on {
+            let (_, _, off_ms) = self.pattern[self.step];
+            self.beeper.set(None);
+            self.on = false;
+            self.next_change = Some(next_change + off_ms as u64);
+        } else {
+            self.step += 1;
+            self.start_step(now);
+        }
+    }
+}
@@ -0,0 +1,103 @@
+/* Keypad admin mode.
+ *
+ * Entered with `*`, an admin code and `#`. Then a command is chosen (followed by `#`):
+ *   1 - change the admin code, 2 - add a user code, 3 - add a one-time guest code,
+ *   4 - remove all user and guest codes, 0 - dump the audit log.
+ * New codes have to be entered twice (each time followed by `#`), `*#` cancels.
+ * Only the entries are handled here, checking and storing codes is up to the caller. */
+
+use alloc::string::String;
+
+use passcode::Role;
+
+pub const MIN_CODE_LEN: usize = 4;
+
+pub enum Reply {
+    /* Command accepted, waiting for the new code */
+    EnterNew(Role),
+    /* New code accepted, waiting for it to be entered again */
+    ConfirmNew,
+    TooShort,
+    /* Only digits are allowed in a code */
+    InvalidKey,
+    UnknownCommand,
+    /* Second entry differs from the first one, nothing changed */
+    Mismatch,
+    Cancelled,
+    /* `Admin` replaces the code of the logged in admin, `User`/`Guest` adds a code */
+    NewCode(Role, String),
+    RemoveCodes,
+    DumpLog,
+}
+
+impl Reply {
+    /* Admin mode is left after these */
+    pub fn is_final(&self) -> bool {
+        matches!(
+            self,
+            Reply::Mismatch | Reply::Cancelled | Reply::NewCode(..) | Reply::RemoveCodes | Reply::DumpLog
+        )
+    }
+}
+
+enum Step {
+    Command,
+    EnterNew(Role),
+    Confirm(Role, String),
+}
+
+pub struct AdminSession {
+    step: Step,
+}
+
+impl AdminSession {
+    pub fn new() -> Self {
+        AdminSession {
+            step: Step::Command,
+        }
+    }
+
+    pub fn enter(&mut self, entry: &str) -> Reply {
+        if entry == "*" {
+            return Reply::Cancelled;
+        }
+
+        match &self.step {
+            Step::Command => {
+                let role = match entry {
+                    "1" => Role::Admin,
+                    "2" => Role::User,
+                    "3" => Role::Guest,
+                    "4" => return Reply::RemoveCodes,
+                    "0" => return Reply::DumpLog,
+                    _ => return Reply::UnknownCommand,
+                };
+                self.step = Step::EnterNew(role);
+                Reply::EnterNew(role)
+            }
+            Step::EnterNew(role) => {
+                if !entry.chars().all(|key| key.is_ascii_digit()) {
+                    Reply::InvalidKey
+                } else if entry.len() < MIN_CODE_LEN {
+                    Reply::TooShort
+                } else {
+                    self.step = Step::Confirm(*role, String::from(entry));
+                    Reply::ConfirmNew
+                }
+            }
+            Step::Confirm(role, new_code) => {
+                if new_code == entry {
+                    Reply::NewCode(*role, new_code.clone())
+                } else {
+                    Reply::Mismatch
+                }
+            }
+        }
+    }
+}
+
+impl Default for AdminSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,79 @@
+/* Ring buffer with the latest access attempts, the oldest records get overwritten.
+ * Timestamps are milliseconds since boot, there is no wall clock on the board. */
+
+use core::fmt;
+
+use passcode::Role;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Granted(Role),
+    Denied,
+    /* Wrong code which locked the keypad (lock time in ms) */
+    LockedOut(u64),
+    AdminMode,
+    CodeAdded(Role),
+    CodeChanged,
+    CodesRemoved,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Record {
+    pub timestamp: u64,
+    /* Slot of the code used, if the code was known */
+    pub slot: Option<usize>,
+    pub outcome: Outcome,
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:>8}.{:03} s  ", self.timestamp / 1000, self.timestamp % 1000)?;
+        match self.slot {
+            Some(slot) => write!(f, "slot {}  ", slot)?,
+            None => write!(f, "slot -  ")?,
+        }
+        match self.outcome {
+            Outcome::Granted(role) => write!(f, "access granted ({:?})", role),
+            Outcome::Denied => write!(f, "wrong code"),
+            Outcome::LockedOut(ms) => write!(f, "wrong code, locked for {} s", ms / 1000),
+            Outcome::AdminMode => write!(f, "admin mode"),
+            Outcome::CodeAdded(role) => write!(f, "code added ({:?})", role),
+            Outcome::CodeChanged => write!(f, "admin code changed"),
+            Outcome::CodesRemoved => write!(f, "user and guest codes removed"),
+        }
+    }
+}
+
+pub struct AuditLog<const N: usize> {
+    records: [Option<Record>; N],
+    next: usize,
+}
+
+impl<const N: usize> AuditLog<N> {
+    pub const fn new() -> Self {
+        AuditLog {
+            records: [None; N],
+            next: 0,
+        }
+    }
+
+    pub fn push(&mut self, timestamp: u64, slot: Option<usize>, outcome: Outcome) {
+        self.records[self.next] = Some(Record {
+            timestamp,
+            slot,
+            outcome,
+        });
+        self.next = (self.next + 1) % N;
+    }
+
+    /* Oldest record first */
+    pub fn iter(&self) -> impl Iterator<Item = &Record> + '_ {
+        (0..N).filter_map(move |i| self.records[(self.next + i) % N].as_ref())
+    }
+}
+
+impl<const N: usize> Default for AuditLog<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
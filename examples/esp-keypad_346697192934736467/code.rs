@@ -0,0 +1,31 @@
+/* Code table in flash, the codes themselves (hashing, roles, the table format) are in the
+ * `passcode` crate */
+
+use embedded_storage::Storage;
+use passcode::{CodeStore, Codes, CODES_LEN};
+
+/* Codes stored at a fixed offset of any embedded-storage flash (esp-storage on the device) */
+pub struct FlashStore<S> {
+    flash: S,
+    offset: u32,
+}
+
+impl<S: Storage> FlashStore<S> {
+    pub fn new(flash: S, offset: u32) -> Self {
+        FlashStore { flash, offset }
+    }
+}
+
+impl<S: Storage> CodeStore for FlashStore<S> {
+    type Error = S::Error;
+
+    fn load(&mut self) -> Result<Option<Codes>, Self::Error> {
+        let mut bytes = [0u8; CODES_LEN];
+        self.flash.read(self.offset, &mut bytes)?;
+        Ok(Codes::from_bytes(&bytes))
+    }
+
+    fn save(&mut self, codes: &Codes) -> Result<(), Self::Error> {
+        self.flash.write(self.offset, &codes.to_bytes())
+    }
+}
@@ -3,17 +3,27 @@
 
 use esp32s2_hal::{
     clock::ClockControl,
+    ledc::{
+        channel::{self, ChannelIFace},
+        timer::{self, TimerIFace},
+        LSGlobalClkSource,
+        LowSpeed,
+        LEDC,
+    },
+    macros::ram,
     peripherals::Peripherals,
     gpio::*,
     prelude::*,
     systimer::SystemTimer,
     timer::TimerGroup,
+    Rng,
     Rtc,
     IO,
     Delay,
 };
 
 use keypad2::{Keypad, Columns, Rows};
+use esp_storage::FlashStorage;
 use esp_println::println;
 use esp_backtrace as _;
 
@@ -37,6 +47,89 @@ fn init_heap() {
 use alloc::string::String;
 use alloc::string;
 
+mod action;
+mod admin;
+mod audit;
+mod code;
+use action::{poll_all, trigger_all, Action, Beeper, Buzzer, DoorLock, Tone, Trigger};
+use admin::{AdminSession, Reply, MIN_CODE_LEN};
+use audit::{AuditLog, Outcome};
+use code::FlashStore;
+use passcode::{CodeStore, Codes, Role, StoredCode, FACTORY_CODE, SALT_LEN};
+use lockout::{save_failures, saved_failures, Lockout, LockoutPolicy, SavedFailures};
+
+/* Flash sector holding the hashed codes (`nvs` partition of the default partition table) */
+const CODE_FLASH_OFFSET: u32 = 0x9000;
+const ADMIN_MENU: &str = "1 - change admin code, 2 - add user, 3 - add guest, 4 - remove users, 0 - audit log";
+/* Number of access attempts kept for the serial dump */
+const AUDIT_LOG_LEN: usize = 64;
+/* How long the lock relay stays open after a correct code */
+const UNLOCK_MS: u64 = 5_000;
+
+/* 3 wrong codes lock the keypad for 30 s, every next wrong code doubles it, up to an hour */
+const LOCKOUT_POLICY: LockoutPolicy = LockoutPolicy {
+    max_attempts: 3,
+    base_delay_ms: 30_000,
+    max_delay_ms: 60 * 60 * 1000,
+};
+
+/* Wrong codes counter lives in RTC fast memory, which keeps its content over a reset,
+ * so resetting the board doesn't clear a lockout. After power-on the memory contains
+ * garbage, `saved_failures` checks it */
+#[ram(rtc_fast, uninitialized)]
+static mut FAILED_ATTEMPTS: SavedFailures = [0; 3];
+
+fn load_failed_attempts() -> u32 {
+    saved_failures(unsafe { FAILED_ATTEMPTS })
+}
+
+fn store_failed_attempts(count: u32) {
+    unsafe { FAILED_ATTEMPTS = save_failures(count) };
+}
+
+fn random_salt(rng: &mut Rng) -> [u8; SALT_LEN] {
+    let mut salt = [0u8; SALT_LEN];
+    for chunk in salt.chunks_mut(4) {
+        chunk.copy_from_slice(&rng.random().to_le_bytes());
+    }
+    salt
+}
+
+/* Buzzer pitches, one LEDC timer each */
+const LOW_TONE_HZ: u32 = 800;
+const MID_TONE_HZ: u32 = 2_000;
+const HIGH_TONE_HZ: u32 = 3_200;
+
+/* Passive buzzer on a LEDC channel, a tone switches the channel to the timer running
+ * at its frequency */
+struct LedcBeeper<'a, O: OutputPin> {
+    channel: channel::Channel<'a, LowSpeed, O>,
+    /* Low, mid and high tone */
+    timers: [&'a timer::Timer<'a, LowSpeed>; 3],
+}
+
+impl<'a, O: OutputPin> Beeper for LedcBeeper<'a, O> {
+    fn set(&mut self, tone: Option<Tone>) {
+        let timer = match tone {
+            None => {
+                self.channel.set_duty(0).unwrap();
+                return;
+            }
+            Some(Tone::Low) => self.timers[0],
+            Some(Tone::Mid) => self.timers[1],
+            Some(Tone::High) => self.timers[2],
+        };
+        self.channel
+            .configure(channel::config::Config { timer, duty_pct: 50 })
+            .unwrap();
+    }
+}
+
+/* Monotonic time in milliseconds */
+fn millis() -> u64 {
+    SystemTimer::now() / (SystemTimer::TICKS_PER_SECOND / 1000)
+}
+
 #[entry]
 fn main() -> ! {
     init_heap();
@@ -67,7 +160,21 @@ fn main() -> ! {
 
     let io = IO::new(peripherals.GPIO, peripherals.IO_MUX);
 
-    let pwd : String = String::from("12345");
+    let mut rng = Rng::new(peripherals.RNG);
+    let mut code_store = FlashStore::new(FlashStorage::new(), CODE_FLASH_OFFSET);
+
+    let mut codes = match code_store.load().unwrap() {
+        Some(codes) => codes,
+        None => {
+            println!("No codes stored yet, using the factory admin code");
+            let mut codes = Codes::new();
+            codes.add(Role::Admin, FACTORY_CODE);
+            code_store.save(&codes).unwrap();
+            codes
+        }
+    };
+
+    let mut audit_log: AuditLog<AUDIT_LOG_LEN> = AuditLog::new();
 
     let mut keypad = Keypad::new((  
                     /* ROWS */
@@ -83,34 +190,195 @@ fn main() -> ! {
         io.pins.gpio0.into_open_drain_output(), // COL3
     ));
 
+    /* Lock relay on gpio7, buzzer on gpio9 */
+    let mut door_lock = DoorLock::new(io.pins.gpio7.into_push_pull_output(), UNLOCK_MS);
+
+    let mut ledc = LEDC::new(peripherals.LEDC, &clocks, &mut system.peripheral_clock_control);
+    ledc.set_global_slow_clock(LSGlobalClkSource::APBClk);
+
+    let mut lstimer0 = ledc.get_timer::<LowSpeed>(timer::Number::Timer0);
+    let mut lstimer1 = ledc.get_timer::<LowSpeed>(timer::Number::Timer1);
+    let mut lstimer2 = ledc.get_timer::<LowSpeed>(timer::Number::Timer2);
+    for (lstimer, frequency) in [
+        (&mut lstimer0, LOW_TONE_HZ),
+        (&mut lstimer1, MID_TONE_HZ),
+        (&mut lstimer2, HIGH_TONE_HZ),
+    ] {
+        lstimer
+            .configure(timer::config::Config {
+                duty: timer::config::Duty::Duty5Bit,
+                clock_source: timer::LSClockSource::APBClk,
+                frequency: frequency.Hz(),
+            })
+            .unwrap();
+    }
+
+    let mut channel0 = ledc.get_channel(channel::Number::Channel0, io.pins.gpio9.into_push_pull_output());
+    channel0
+        .configure(channel::config::Config {
+            timer: &lstimer0,
+            duty_pct: 0,
+        })
+        .unwrap();
+
+    let mut buzzer = Buzzer::new(LedcBeeper {
+        channel: channel0,
+        timers: [&lstimer0, &lstimer1, &lstimer2],
+    });
+
+    let mut actions: [&mut dyn Action; 2] = [&mut door_lock, &mut buzzer];
+
     let mut user_pwd: String = String::new();
 
+    let mut lockout = Lockout::restore(LOCKOUT_POLICY, load_failed_attempts(), millis());
+    /* Slot of the logged in admin and the admin mode state */
+    let mut admin: Option<(usize, AdminSession)> = None;
+
     loop {
+        poll_all(&mut actions, millis());
+
         let mut key = keypad.read_char(&mut delay);
 
         if key != ' '
         {
+            let now = millis();
+
+            if lockout.is_locked(now) {
+                /* Ignore everything typed while locked */
+                user_pwd.clear();
+                println!("Keypad locked, try again in {} s", (lockout.remaining_ms(now) + 999) / 1000);
+                trigger_all(&mut actions, Trigger::Denied, now);
+            }
             /* Use # as an Enter button */
-            if key == '#' {
-                if user_pwd == pwd
-                {
-                    /* Some additional callback here */
-                    println!("Correct!");
+            else if key == '#' {
+                if let Some((admin_slot, session)) = admin.as_mut() {
+                    let admin_slot = *admin_slot;
+                    let reply = session.enter(&user_pwd);
+                    let done = reply.is_final();
+                    let mut feedback = match reply {
+                        Reply::TooShort | Reply::InvalidKey | Reply::UnknownCommand | Reply::Mismatch => Trigger::Denied,
+                        _ => Trigger::Confirm,
+                    };
+
+                    match reply {
+                        Reply::EnterNew(role) => println!("Enter the new {:?} code followed by #", role),
+                        Reply::ConfirmNew => println!("Enter the new code again, followed by #"),
+                        Reply::TooShort => println!("Code too short, use at least {} digits! Enter the new code...", MIN_CODE_LEN),
+                        Reply::InvalidKey => println!("Code can only contain digits! Enter the new code..."),
+                        Reply::UnknownCommand => println!("Unknown command! {}", ADMIN_MENU),
+                        Reply::Mismatch => println!("Codes don't match, code NOT changed"),
+                        Reply::Cancelled => println!("Admin mode left"),
+                        Reply::NewCode(role, new_code) => {
+                            if codes.find(&new_code).map_or(false, |(slot, _)| slot != admin_slot || role != Role::Admin) {
+                                println!("Code already in use, NOT saved");
+                                feedback = Trigger::Denied;
+                            } else {
+                                let stored = StoredCode::new(&new_code, random_salt(&mut rng));
+                                let saved = match role {
+                                    Role::Admin => {
+                                        codes.set(admin_slot, Role::Admin, stored);
+                                        Some((admin_slot, Outcome::CodeChanged))
+                                    }
+                                    role => codes.add(role, stored).map(|slot| (slot, Outcome::CodeAdded(role))),
+                                };
+
+                                match saved {
+                                    Some((slot, outcome)) => {
+                                        code_store.save(&codes).unwrap();
+                                        audit_log.push(now, Some(slot), outcome);
+                                        println!("Code saved to slot {}!", slot);
+                                    }
+                                    None => {
+                                        println!("No free slot left, remove some codes first");
+                                        feedback = Trigger::Denied;
+                                    }
+                                }
+                            }
+                        }
+                        Reply::RemoveCodes => {
+                            codes.remove_non_admins();
+                            code_store.save(&codes).unwrap();
+                            audit_log.push(now, Some(admin_slot), Outcome::CodesRemoved);
+                            println!("User and guest codes removed");
+                        }
+                        Reply::DumpLog => {
+                            println!("--- Audit log ---");
+                            for record in audit_log.iter() {
+                                println!("{}", record);
+                            }
+                            println!("-----------------");
+                        }
+                    }
+
+                    trigger_all(&mut actions, feedback, now);
+
+                    if done {
+                        admin = None;
+                    }
                     user_pwd.clear();
                 }
-                else 
+                else
                 {
+                    /* `*` in front of the code asks for admin mode */
+                    let (admin_request, entered) = match user_pwd.strip_prefix('*') {
+                        Some(entered) => (true, entered),
+                        None => (false, user_pwd.as_str()),
+                    };
+
+                    match codes.find(entered) {
+                        Some((slot, role)) if !admin_request || role == Role::Admin => {
+                            lockout.record_success();
+                            store_failed_attempts(0);
+
+                            if admin_request {
+                                audit_log.push(now, Some(slot), Outcome::AdminMode);
+                                println!("Admin mode: {} (*# to leave)", ADMIN_MENU);
+                                trigger_all(&mut actions, Trigger::Confirm, now);
+                                admin = Some((slot, AdminSession::new()));
+                            } else {
+                                audit_log.push(now, Some(slot), Outcome::Granted(role));
+                                println!("Correct! ({:?})", role);
+                                trigger_all(&mut actions, Trigger::Granted, now);
+
+                                if role == Role::Guest {
+                                    codes.remove(slot);
+                                    code_store.save(&codes).unwrap();
+                                    println!("One-time guest code used up");
+                                }
+                            }
+                        }
+                        _ => {
+                            let locked_for = lockout.record_failure(now);
+                            store_failed_attempts(lockout.failures());
+                            trigger_all(&mut actions, Trigger::Denied, now);
+
+                            match locked_for {
+                                Some(ms) => {
+                                    audit_log.push(now, None, Outcome::LockedOut(ms));
+                                    println!("Wrong password! Keypad locked for {} s", ms / 1000);
+                                }
+                                None => {
+                                    audit_log.push(now, None, Outcome::Denied);
+                                    println!("Wrong password! Try again...");
+                                }
+                            }
+                        }
+                    }
                     user_pwd.clear();
-                    println!("Wrong password! Try again...");
                 }
             }
             else 
             {
-                println!("{}", key);
                 user_pwd.push(key);
+                /* Codes (new admin codes too) are secret, only show how much was typed */
+                println!("{}", "*".repeat(user_pwd.len()));
+                trigger_all(&mut actions, Trigger::KeyPress, now);
                 
             }
-            while (key != ' ') { key = keypad.read_char(&mut delay);}
+            while (key != ' ') {
+                poll_all(&mut actions, millis());
+                key = keypad.read_char(&mut delay);
+            }
         }
     }
 }
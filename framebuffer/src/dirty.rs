@@ -0,0 +1,197 @@
+use embedded_graphics_core::{geometry::Point, primitives::Rectangle};
+
+/* Up to `N` rectangles covering everything that changed since the last flush.
+ *
+ * A new rectangle overlapping or touching a stored one is merged into it. When all slots are
+ * taken it's merged with the one whose bounding box adds the least area, so the list never
+ * overflows, it only gets coarser. */
+#[derive(Clone, Copy, Debug)]
+pub struct DirtyRegions<const N: usize> {
+    regions: [Rectangle; N],
+    len: usize,
+}
+
+impl<const N: usize> DirtyRegions<N> {
+    pub const fn new() -> Self {
+        DirtyRegions {
+            regions: [Rectangle::zero(); N],
+            len: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Rectangle> + '_ {
+        self.regions[..self.len].iter()
+    }
+
+    pub fn add(&mut self, area: Rectangle) {
+        if area.is_zero_sized() || N == 0 {
+            return;
+        }
+
+        let mut area = area;
+        loop {
+            /* Merging grows the rectangle, which can make it touch others stored before */
+            let merge = match self.regions[..self.len].iter().position(|region| touches(region, &area)) {
+                Some(i) => Some(i),
+                None if self.len == N => Some(self.cheapest_merge(&area)),
+                None => None,
+            };
+
+            match merge {
+                Some(i) => {
+                    area = bounding_box(&self.regions[i], &area);
+                    self.regions[i] = self.regions[self.len - 1];
+                    self.len -= 1;
+                }
+                None => break,
+            }
+        }
+
+        self.regions[self.len] = area;
+        self.len += 1;
+    }
+
+    /* Region whose bounding box with `area` adds the least extra area */
+    fn cheapest_merge(&self, area: &Rectangle) -> usize {
+        let added = |region: &Rectangle| {
+            pixel_count(&bounding_box(region, area)).saturating_sub(pixel_count(region) + pixel_count(area))
+        };
+        (0..self.len).min_by_key(|&i| added(&self.regions[i])).unwrap()
+    }
+}
+
+impl<const N: usize> Default for DirtyRegions<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn pixel_count(rect: &Rectangle) -> u64 {
+    rect.size.width as u64 * rect.size.height as u64
+}
+
+/* Overlapping, or sharing an edge */
+fn touches(a: &Rectangle, b: &Rectangle) -> bool {
+    !a.offset(1).intersection(b).is_zero_sized()
+}
+
+pub fn bounding_box(a: &Rectangle, b: &Rectangle) -> Rectangle {
+    let (a_end, b_end) = (a.bottom_right().unwrap(), b.bottom_right().unwrap());
+    Rectangle::with_corners(a.top_left.component_min(b.top_left), a_end.component_max(b_end))
+}
+
+/* Bounding box of changed pixels, grown point by point while drawing */
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Extent {
+    corners: Option<(Point, Point)>,
+}
+
+impl Extent {
+    pub fn include(&mut self, point: Point) {
+        self.corners = Some(match self.corners {
+            Some((min, max)) => (min.component_min(point), max.component_max(point)),
+            None => (point, point),
+        });
+    }
+
+    pub fn to_rectangle(self) -> Option<Rectangle> {
+        self.corners.map(|(min, max)| Rectangle::with_corners(min, max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics_core::geometry::Size;
+
+    fn rect(x: i32, y: i32, width: u32, height: u32) -> Rectangle {
+        Rectangle::new(Point::new(x, y), Size::new(width, height))
+    }
+
+    fn regions<const N: usize>(dirty: &DirtyRegions<N>) -> ([Rectangle; N], usize) {
+        let mut list = [Rectangle::zero(); N];
+        let mut len = 0;
+        for region in dirty.iter() {
+            list[len] = *region;
+            len += 1;
+        }
+        list[..len].sort_by_key(|region| (region.top_left.y, region.top_left.x));
+        (list, len)
+    }
+
+    #[test]
+    fn touching_and_overlapping_regions_merge() {
+        let mut dirty = DirtyRegions::<4>::new();
+        dirty.add(rect(0, 0, 10, 10));
+        dirty.add(rect(10, 0, 10, 10));
+        dirty.add(rect(5, 5, 10, 10));
+
+        let (list, len) = regions(&dirty);
+        assert_eq!(len, 1);
+        assert_eq!(list[0], rect(0, 0, 20, 15));
+    }
+
+    #[test]
+    fn separate_regions_stay_apart() {
+        let mut dirty = DirtyRegions::<4>::new();
+        dirty.add(rect(0, 0, 10, 10));
+        dirty.add(rect(11, 0, 10, 10));
+        dirty.add(rect(0, 20, 5, 5));
+
+        let (list, len) = regions(&dirty);
+        assert_eq!(len, 3);
+        assert_eq!(list[..len], [rect(0, 0, 10, 10), rect(11, 0, 10, 10), rect(0, 20, 5, 5)]);
+    }
+
+    #[test]
+    fn grown_region_swallows_the_ones_it_reaches() {
+        let mut dirty = DirtyRegions::<4>::new();
+        dirty.add(rect(0, 0, 4, 4));
+        dirty.add(rect(20, 0, 4, 4));
+        /* Touches the first one, the bounding box then covers the second */
+        dirty.add(rect(4, 0, 20, 2));
+
+        let (list, len) = regions(&dirty);
+        assert_eq!(len, 1);
+        assert_eq!(list[0], rect(0, 0, 24, 4));
+    }
+
+    #[test]
+    fn overflow_merges_the_closest_regions() {
+        let mut dirty = DirtyRegions::<2>::new();
+        dirty.add(rect(0, 0, 10, 10));
+        dirty.add(rect(100, 100, 10, 10));
+        dirty.add(rect(0, 12, 10, 10));
+
+        let (list, len) = regions(&dirty);
+        assert_eq!(len, 2);
+        assert_eq!(list[..len], [rect(0, 0, 10, 22), rect(100, 100, 10, 10)]);
+    }
+
+    #[test]
+    fn overflow_of_a_single_slot_collapses_to_the_bounding_box() {
+        let mut dirty = DirtyRegions::<1>::new();
+        dirty.add(rect(0, 0, 10, 10));
+        dirty.add(rect(50, 30, 10, 10));
+        dirty.add(rect(20, 60, 1, 1));
+
+        let (list, len) = regions(&dirty);
+        assert_eq!(len, 1);
+        assert_eq!(list[0], rect(0, 0, 60, 61));
+    }
+
+    #[test]
+    fn empty_areas_are_ignored() {
+        let mut dirty = DirtyRegions::<2>::new();
+        dirty.add(rect(5, 5, 0, 10));
+        assert!(dirty.is_empty());
+    }
+}
@@ -0,0 +1,278 @@
+/* RAM framebuffer for the display examples.
+ *
+ * Drawing goes into a buffer in RAM and only the changed regions are sent to the panel, each
+ * in a single `set_pixels` window. A clear followed by a redraw doesn't flicker any more, the
+ * panel only gets the final picture, and drawing what's already there costs no SPI traffic.
+ * Works with any driver: `flush` hands out the region and its colors (row by row) to a
+ * closure that writes them to the panel.
+ *
+ * A full 320x240 Rgb565 buffer takes 150 KB, which fits the ESP32-C3 (400 KB SRAM). When
+ * that's too much (ESP32-S2, or next to Wi-Fi) give it a smaller buffer: the screen is then
+ * rendered in horizontal bands (tiles) as tall as the buffer allows, see `render`. */
+
+#![no_std]
+
+pub mod dirty;
+
+use embedded_graphics_core::{
+    draw_target::DrawTarget,
+    geometry::{OriginDimensions, Point, Size},
+    pixelcolor::PixelColor,
+    primitives::{PointsIter, Rectangle},
+    Pixel,
+};
+
+use dirty::{DirtyRegions, Extent};
+
+/* More regions means less overdraw on flush, but merging gets slower */
+pub const MAX_DIRTY_REGIONS: usize = 8;
+
+pub struct FrameBuffer<'a, C> {
+    buffer: &'a mut [C],
+    screen: Size,
+    /* Part of the screen held in `buffer`, all of it unless tiled */
+    window: Rectangle,
+    background: C,
+    dirty: DirtyRegions<MAX_DIRTY_REGIONS>,
+}
+
+impl<'a, C: PixelColor> FrameBuffer<'a, C> {
+    /* Tiled if `buffer` is shorter than `screen.width * screen.height`, it has to hold at
+     * least one row. The buffer starts out as `background`, which is also what tiles are
+     * reset to before they are drawn */
+    pub fn new(buffer: &'a mut [C], screen: Size, background: C) -> Self {
+        let rows = (buffer.len() / screen.width as usize).min(screen.height as usize) as u32;
+        assert!(rows > 0, "framebuffer can't hold a single row");
+
+        buffer.fill(background);
+        FrameBuffer {
+            buffer,
+            screen,
+            window: Rectangle::new(Point::zero(), Size::new(screen.width, rows)),
+            background,
+            dirty: DirtyRegions::new(),
+        }
+    }
+
+    pub fn is_tiled(&self) -> bool {
+        self.window.size != self.screen
+    }
+
+    /* Marks `area` as changed, so the next flush sends it even if nothing was drawn there,
+     * e.g. after the panel was reset or something was drawn on it directly */
+    pub fn invalidate(&mut self, area: &Rectangle) {
+        self.dirty.add(area.intersection(&self.window));
+    }
+
+    /* Writes every changed region with one call of `write(region, colors)`, the colors come
+     * row by row, left to right. */
+    pub fn flush<E>(
+        &mut self,
+        mut write: impl FnMut(&Rectangle, &mut dyn Iterator<Item = C>) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let dirty = self.dirty;
+        self.dirty.clear();
+
+        for region in dirty.iter() {
+            let buffer = &*self.buffer;
+            let window = self.window;
+            let mut colors = region.points().map(|point| buffer[index(&window, point)]);
+            write(region, &mut colors)?;
+        }
+        Ok(())
+    }
+
+    /* Draws the screen with `draw` and flushes it. When tiled the screen is drawn tile by tile:
+     * each tile starts out as `background`, `draw` has to redraw everything visible on it
+     * (drawing outside the tile is clipped) and the tile is flushed before the next one */
+    pub fn render<E>(
+        &mut self,
+        mut draw: impl FnMut(&mut Self),
+        mut write: impl FnMut(&Rectangle, &mut dyn Iterator<Item = C>) -> Result<(), E>,
+    ) -> Result<(), E> {
+        if !self.is_tiled() {
+            draw(self);
+            return self.flush(write);
+        }
+
+        let rows = self.window.size.height;
+        for top in (0..self.screen.height).step_by(rows as usize) {
+            self.window = Rectangle::new(
+                Point::new(0, top as i32),
+                Size::new(self.screen.width, rows.min(self.screen.height - top)),
+            );
+            self.buffer.fill(self.background);
+            self.dirty.clear();
+
+            draw(self);
+            self.flush(&mut write)?;
+        }
+
+        self.window = Rectangle::new(Point::zero(), Size::new(self.screen.width, rows));
+        self.buffer.fill(self.background);
+        Ok(())
+    }
+
+    fn set(&mut self, point: Point, color: C, changed: &mut Extent) {
+        if !self.window.contains(point) {
+            return;
+        }
+        let tiled = self.is_tiled();
+        let pixel = &mut self.buffer[index(&self.window, point)];
+        /* A tile starts as background, it has to be sent even where it's drawn background */
+        if *pixel != color || tiled {
+            *pixel = color;
+            changed.include(point);
+        }
+    }
+}
+
+fn index(window: &Rectangle, point: Point) -> usize {
+    let offset = point - window.top_left;
+    offset.y as usize * window.size.width as usize + offset.x as usize
+}
+
+impl<C: PixelColor> OriginDimensions for FrameBuffer<'_, C> {
+    fn size(&self) -> Size {
+        self.screen
+    }
+}
+
+impl<C: PixelColor> DrawTarget for FrameBuffer<'_, C> {
+    type Color = C;
+    type Error = core::convert::Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<C>>,
+    {
+        let mut changed = Extent::default();
+        for Pixel(point, color) in pixels {
+            self.set(point, color, &mut changed);
+        }
+        if let Some(area) = changed.to_rectangle() {
+            self.dirty.add(area);
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: C) -> Result<(), Self::Error> {
+        let mut changed = Extent::default();
+        for point in area.intersection(&self.window).points() {
+            self.set(point, color, &mut changed);
+        }
+        if let Some(area) = changed.to_rectangle() {
+            self.dirty.add(area);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics_core::{
+        pixelcolor::{Rgb565, RgbColor},
+        Drawable,
+    };
+
+    const SCREEN: Size = Size::new(4, 5);
+
+    /* Every `write` call, with the number of colors it got and whether they were all `color` */
+    fn written(
+        screen: &mut FrameBuffer<Rgb565>,
+        color: Rgb565,
+        mut draw: impl FnMut(&mut FrameBuffer<Rgb565>),
+    ) -> ([(Rectangle, usize, bool); 8], usize, usize) {
+        let mut writes = [(Rectangle::zero(), 0, false); 8];
+        let mut len = 0;
+        let mut draws = 0;
+        screen
+            .render(
+                |screen| {
+                    draws += 1;
+                    draw(screen)
+                },
+                |area, colors| {
+                    let mut count = 0;
+                    let mut all = true;
+                    for pixel in colors {
+                        count += 1;
+                        all &= pixel == color;
+                    }
+                    writes[len] = (*area, count, all);
+                    len += 1;
+                    Ok::<(), ()>(())
+                },
+            )
+            .unwrap();
+        (writes, len, draws)
+    }
+
+    fn fill(screen: &mut FrameBuffer<Rgb565>, color: Rgb565) {
+        screen.fill_solid(&Rectangle::new(Point::zero(), SCREEN), color).unwrap();
+    }
+
+    #[test]
+    fn flush_sends_only_what_changed() {
+        let mut buffer = [Rgb565::BLACK; 20];
+        let mut screen = FrameBuffer::new(&mut buffer, SCREEN, Rgb565::BLACK);
+        assert!(!screen.is_tiled());
+
+        let (writes, len, draws) = written(&mut screen, Rgb565::RED, |screen| {
+            Pixel(Point::new(1, 1), Rgb565::RED).draw(screen).unwrap();
+            Pixel(Point::new(3, 4), Rgb565::RED).draw(screen).unwrap();
+        });
+        assert_eq!(draws, 1);
+        assert_eq!(len, 2);
+        assert_eq!(writes[0], (Rectangle::new(Point::new(1, 1), Size::new(1, 1)), 1, true));
+        assert_eq!(writes[1], (Rectangle::new(Point::new(3, 4), Size::new(1, 1)), 1, true));
+
+        /* Already on the screen */
+        let (_, len, _) = written(&mut screen, Rgb565::RED, |screen| {
+            Pixel(Point::new(1, 1), Rgb565::RED).draw(screen).unwrap();
+        });
+        assert_eq!(len, 0);
+    }
+
+    #[test]
+    fn invalidated_area_is_sent_again() {
+        let mut buffer = [Rgb565::BLACK; 20];
+        let mut screen = FrameBuffer::new(&mut buffer, SCREEN, Rgb565::BLACK);
+        let area = Rectangle::new(Point::new(0, 3), Size::new(4, 2));
+
+        let (writes, len, _) = written(&mut screen, Rgb565::BLACK, |screen| screen.invalidate(&area));
+        assert_eq!(len, 1);
+        assert_eq!(writes[0], (area, 8, true));
+    }
+
+    #[test]
+    fn tiles_cover_the_screen_band_by_band() {
+        /* Two rows: bands at rows 0-1, 2-3 and a short one at row 4 */
+        let mut buffer = [Rgb565::BLACK; 9];
+        let mut screen = FrameBuffer::new(&mut buffer, SCREEN, Rgb565::BLACK);
+        assert!(screen.is_tiled());
+
+        let (writes, len, draws) = written(&mut screen, Rgb565::GREEN, |screen| fill(screen, Rgb565::GREEN));
+        assert_eq!(draws, 3);
+        assert_eq!(len, 3);
+        assert_eq!(writes[0], (Rectangle::new(Point::new(0, 0), Size::new(4, 2)), 8, true));
+        assert_eq!(writes[1], (Rectangle::new(Point::new(0, 2), Size::new(4, 2)), 8, true));
+        assert_eq!(writes[2], (Rectangle::new(Point::new(0, 4), Size::new(4, 1)), 4, true));
+    }
+
+    #[test]
+    fn tiles_start_as_background_and_clip_the_drawing() {
+        let mut buffer = [Rgb565::BLACK; 8];
+        let mut screen = FrameBuffer::new(&mut buffer, SCREEN, Rgb565::BLACK);
+        fill(&mut screen, Rgb565::GREEN);
+
+        /* Background drawn on a tile is sent too, it isn't what the panel shows */
+        let (writes, len, _) = written(&mut screen, Rgb565::BLACK, |screen| {
+            screen.fill_solid(&Rectangle::new(Point::new(1, 1), Size::new(2, 2)), Rgb565::BLACK).unwrap();
+        });
+        assert_eq!(len, 2);
+        assert_eq!(writes[0], (Rectangle::new(Point::new(1, 1), Size::new(2, 1)), 2, true));
+        assert_eq!(writes[1], (Rectangle::new(Point::new(1, 2), Size::new(2, 1)), 2, true));
+    }
+}
@@ -0,0 +1,127 @@
+/* Direction of one detent step */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rotation {
+    Clockwise,
+    CounterClockwise,
+}
+
+const START: u8 = 0x0;
+const CW_FINAL: u8 = 0x1;
+const CW_BEGIN: u8 = 0x2;
+const CW_NEXT: u8 = 0x3;
+const CCW_BEGIN: u8 = 0x4;
+const CCW_FINAL: u8 = 0x5;
+const CCW_NEXT: u8 = 0x6;
+
+const DIR_CW: u8 = 0x10;
+const DIR_CCW: u8 = 0x20;
+
+/* Next state for every state (rows) and A/B level pair (columns, `(a << 1) | b`) */
+const TRANSITIONS: [[u8; 4]; 7] = [
+    /* START */     [START,     CW_BEGIN,  CCW_BEGIN, START],
+    /* CW_FINAL */  [CW_NEXT,   START,     CW_FINAL,  START | DIR_CW],
+    /* CW_BEGIN */  [CW_NEXT,   CW_BEGIN,  START,     START],
+    /* CW_NEXT */   [CW_NEXT,   CW_BEGIN,  CW_FINAL,  START],
+    /* CCW_BEGIN */ [CCW_NEXT,  START,     CCW_BEGIN, START],
+    /* CCW_FINAL */ [CCW_NEXT,  CCW_FINAL, START,     START | DIR_CCW],
+    /* CCW_NEXT */  [CCW_NEXT,  CCW_FINAL, CCW_BEGIN, START],
+];
+
+/* Quadrature decoder for mechanical (KY-040 like) rotary encoders, one step per detent.
+ *
+ * Feed it the A (CLK) and B (DT) levels whenever one of them changes. A step is only
+ * reported once the whole Gray code sequence between two detents was seen, so contact
+ * bounce (jumping back and forth between two neighbouring states) never produces a step.
+ * Both lines are expected to be high at a detent (pull-up inputs), A falling first means clockwise. */
+pub struct Encoder {
+    state: u8,
+}
+
+impl Encoder {
+    pub const fn new() -> Self {
+        Encoder { state: START }
+    }
+
+    pub fn update(&mut self, a: bool, b: bool) -> Option<Rotation> {
+        let levels = ((a as usize) << 1) | b as usize;
+        self.state = TRANSITIONS[(self.state & 0x0f) as usize][levels];
+
+        match self.state & 0x30 {
+            DIR_CW => Some(Rotation::Clockwise),
+            DIR_CCW => Some(Rotation::CounterClockwise),
+            _ => None,
+        }
+    }
+}
+
+impl Default for Encoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* Recorded A/B levels (1 = high), returns the clockwise and counter-clockwise steps */
+    fn play(levels: &[(u8, u8)]) -> (u32, u32) {
+        let mut encoder = Encoder::new();
+        let (mut cw, mut ccw) = (0, 0);
+        for &(a, b) in levels {
+            match encoder.update(a == 1, b == 1) {
+                Some(Rotation::Clockwise) => cw += 1,
+                Some(Rotation::CounterClockwise) => ccw += 1,
+                None => {}
+            }
+        }
+        (cw, ccw)
+    }
+
+    const CW_DETENT: [(u8, u8); 4] = [(0, 1), (0, 0), (1, 0), (1, 1)];
+    const CCW_DETENT: [(u8, u8); 4] = [(1, 0), (0, 0), (0, 1), (1, 1)];
+
+    #[test]
+    fn one_step_per_detent() {
+        assert_eq!(play(&CW_DETENT), (1, 0));
+        assert_eq!(play(&CCW_DETENT), (0, 1));
+
+        let mut there_and_back = [(1, 1); 12];
+        there_and_back[..4].copy_from_slice(&CW_DETENT);
+        there_and_back[4..8].copy_from_slice(&CW_DETENT);
+        there_and_back[8..].copy_from_slice(&CCW_DETENT);
+        assert_eq!(play(&there_and_back), (2, 1));
+    }
+
+    #[test]
+    fn half_way_and_back_is_no_step() {
+        assert_eq!(play(&[(0, 1), (0, 0), (0, 1), (1, 1)]), (0, 0));
+        assert_eq!(play(&[(1, 0), (1, 1)]), (0, 0));
+    }
+
+    #[test]
+    fn bounce_on_one_channel() {
+        /* A bounces when it starts to fall, B bounces in the middle of the step */
+        let cw = [(0, 1), (1, 1), (0, 1), (1, 1), (0, 1), (0, 0), (0, 1), (0, 0), (1, 0), (1, 1)];
+        assert_eq!(play(&cw), (1, 0));
+
+        /* B bounces around the detent at the end */
+        let ccw = [(1, 0), (0, 0), (0, 1), (1, 1), (1, 0), (1, 1)];
+        assert_eq!(play(&ccw), (0, 1));
+    }
+
+    #[test]
+    fn illegal_jumps_are_no_step() {
+        /* Both lines changing at once (a missed sample) can't tell the direction */
+        assert_eq!(play(&[(0, 0), (1, 1)]), (0, 0));
+        assert_eq!(play(&[(0, 1), (1, 0), (1, 1)]), (0, 0));
+        assert_eq!(play(&[(0, 1), (0, 0), (1, 1)]), (0, 0));
+        assert_eq!(play(&[(1, 0), (0, 0), (1, 1)]), (0, 0));
+
+        /* and the decoder is back at the detent for the next step */
+        let mut after_jump = [(1, 1); 6];
+        after_jump[..2].copy_from_slice(&[(0, 0), (1, 1)]);
+        after_jump[2..].copy_from_slice(&CW_DETENT);
+        assert_eq!(play(&after_jump), (1, 0));
+    }
+}
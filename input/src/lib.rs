@@ -0,0 +1,514 @@
+/* Button input shared by the examples: debouncing, gestures (long press, double click,
+ * auto-repeat), a lock-free queue for edges captured in a GPIO interrupt and a rotary
+ * encoder decoder. Works with embedded-hal 0.2 and 1.0 pins, active-low and active-high wiring. */
+
+#![no_std]
+
+pub mod encoder;
+pub mod pin;
+pub mod queue;
+
+pub use encoder::{Encoder, Rotation};
+pub use pin::{InputLevel, Polarity};
+#[cfg(feature = "eh02")]
+pub use pin::Eh02;
+
+/* Default time a new pin level has to stay stable before it's reported */
+pub const DEFAULT_DEBOUNCE_MS: u64 = 30;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    Pressed,
+    Released,
+    /* Button held for `long_press_ms`, reported once per press */
+    LongPress,
+    /* Second press within `double_click_ms` after a release, reported instead of `Pressed` */
+    DoubleClick,
+    /* Reported every `repeat_interval_ms` while held, after `repeat_delay_ms` */
+    Repeat,
+    Nothing,
+}
+
+/* Debouncing algorythm
+ *
+ * Pure state machine: feed it the raw level of the pin together with a monotonic
+ * timestamp (in milliseconds) and it reports `Pressed`/`Released` once the level
+ * stayed the same for `window_ms`. No busy waiting, so it doesn't stall the main loop. */
+pub struct Debouncer {
+    window_ms: u64,
+    pressed: bool,
+    raw: bool,
+    changed_at: u64,
+    settled_at: u64,
+}
+
+impl Debouncer {
+    pub const fn new(window_ms: u64) -> Self {
+        Debouncer {
+            window_ms,
+            pressed: false,
+            raw: false,
+            changed_at: 0,
+            settled_at: 0,
+        }
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+
+    /* When the last reported level became stable, which is earlier than the `now` it was
+     * reported at if the samples came late (edges replayed from a queue) */
+    pub fn settled_at(&self) -> u64 {
+        self.settled_at
+    }
+
+    pub fn update(&mut self, raw: bool, now: u64) -> Event {
+        if raw != self.raw {
+            /* The previous level might have settled before this edge (edges replayed from a queue) */
+            let event = self.tick(now);
+
+            /* Every edge (including bounces) restarts the stable window */
+            self.raw = raw;
+            self.changed_at = now;
+            return event;
+        }
+
+        self.tick(now)
+    }
+
+    /* Reports the current level once it stayed stable long enough, without a new pin sample */
+    pub fn tick(&mut self, now: u64) -> Event {
+        if self.raw != self.pressed && now.saturating_sub(self.changed_at) >= self.window_ms {
+            self.pressed = self.raw;
+            self.settled_at = self.changed_at + self.window_ms;
+            if self.pressed {
+                Event::Pressed
+            } else {
+                Event::Released
+            }
+        } else {
+            Event::Nothing
+        }
+    }
+}
+
+/* Gesture timings (milliseconds), `None` switches the gesture off */
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GestureConfig {
+    pub long_press_ms: Option<u64>,
+    pub double_click_ms: Option<u64>,
+    pub repeat_delay_ms: Option<u64>,
+    pub repeat_interval_ms: u64,
+}
+
+impl GestureConfig {
+    pub const fn new() -> Self {
+        GestureConfig {
+            long_press_ms: None,
+            double_click_ms: None,
+            repeat_delay_ms: None,
+            repeat_interval_ms: 0,
+        }
+    }
+
+    pub const fn long_press(mut self, ms: u64) -> Self {
+        self.long_press_ms = Some(ms);
+        self
+    }
+
+    pub const fn double_click(mut self, ms: u64) -> Self {
+        self.double_click_ms = Some(ms);
+        self
+    }
+
+    pub const fn repeat(mut self, delay_ms: u64, interval_ms: u64) -> Self {
+        self.repeat_delay_ms = Some(delay_ms);
+        self.repeat_interval_ms = interval_ms;
+        self
+    }
+}
+
+/* Turns debounced `Pressed`/`Released` events into gestures.
+ * Has to be updated on every poll (also with `Event::Nothing`), since
+ * long press and auto-repeat are driven by time only. */
+pub struct Gestures {
+    config: GestureConfig,
+    pressed_at: Option<u64>,
+    next_repeat: u64,
+    long_press_sent: bool,
+    double_click_sent: bool,
+    released_at: Option<u64>,
+}
+
+impl Gestures {
+    pub const fn new(config: GestureConfig) -> Self {
+        Gestures {
+            config,
+            pressed_at: None,
+            next_repeat: 0,
+            long_press_sent: false,
+            double_click_sent: false,
+            released_at: None,
+        }
+    }
+
+    pub fn update(&mut self, event: Event, now: u64) -> Event {
+        match event {
+            Event::Pressed => {
+                let double = match (self.config.double_click_ms, self.released_at) {
+                    (Some(window), Some(released_at)) => now.saturating_sub(released_at) <= window,
+                    _ => false,
+                };
+
+                self.pressed_at = Some(now);
+                self.next_repeat = now + self.config.repeat_delay_ms.unwrap_or(0);
+                self.long_press_sent = false;
+                self.double_click_sent = double;
+                self.released_at = None;
+
+                if double {
+                    Event::DoubleClick
+                } else {
+                    Event::Pressed
+                }
+            }
+            Event::Released => {
+                /* Long presses and the second click of a double click don't start a new double click */
+                self.released_at = if self.long_press_sent || self.double_click_sent {
+                    None
+                } else {
+                    Some(now)
+                };
+                self.pressed_at = None;
+                Event::Released
+            }
+            Event::Nothing => self.tick(now),
+            other => other,
+        }
+    }
+
+    fn tick(&mut self, now: u64) -> Event {
+        let pressed_at = match self.pressed_at {
+            Some(pressed_at) => pressed_at,
+            None => return Event::Nothing,
+        };
+
+        if let Some(long_press) = self.config.long_press_ms {
+            if !self.long_press_sent && now.saturating_sub(pressed_at) >= long_press {
+                self.long_press_sent = true;
+                return Event::LongPress;
+            }
+        }
+
+        if self.config.repeat_delay_ms.is_some() && now >= self.next_repeat {
+            /* If the main loop was busy for longer than an interval, don't fire a burst of repeats */
+            self.next_repeat = now + self.config.repeat_interval_ms.max(1);
+            return Event::Repeat;
+        }
+
+        Event::Nothing
+    }
+}
+
+/* Debouncer and gesture detection for one button, without owning the pin.
+ * Used directly when the pin level comes from somewhere else (e.g. edges queued by an interrupt).
+ *
+ * Gestures see every debounced event at the time it settled, so a press replayed late from a
+ * queue keeps its length. A long press or repeat that was due before such an event is reported
+ * first and the event is held back, `tick` while `has_pending` before feeding new levels. */
+pub struct ButtonState {
+    debouncer: Debouncer,
+    gestures: Gestures,
+    /* Debounced events not given to the gestures yet, with the time they settled */
+    pending: [Option<(Event, u64)>; 2],
+}
+
+impl ButtonState {
+    pub const fn new(window_ms: u64, config: GestureConfig) -> Self {
+        ButtonState {
+            debouncer: Debouncer::new(window_ms),
+            gestures: Gestures::new(config),
+            pending: [None; 2],
+        }
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        self.debouncer.is_pressed()
+    }
+
+    pub fn has_pending(&self) -> bool {
+        self.pending[0].is_some()
+    }
+
+    /* `pressed` is the raw pin level, `now` a monotonic timestamp in milliseconds */
+    pub fn update(&mut self, pressed: bool, now: u64) -> Event {
+        let event = self.debouncer.update(pressed, now);
+        self.hold(event);
+        self.next(now)
+    }
+
+    /* Advances debounce and gesture timers when no new pin level is available */
+    pub fn tick(&mut self, now: u64) -> Event {
+        let event = self.debouncer.tick(now);
+        self.hold(event);
+        self.next(now)
+    }
+
+    fn hold(&mut self, event: Event) {
+        if event == Event::Nothing {
+            return;
+        }
+        if let [Some((oldest, settled_at)), Some(next)] = self.pending {
+            /* Only when new levels came in without draining, the oldest skips the gesture timers */
+            self.gestures.update(oldest, settled_at);
+            self.pending = [Some(next), None];
+        }
+        let slot = if self.pending[0].is_none() { 0 } else { 1 };
+        self.pending[slot] = Some((event, self.debouncer.settled_at()));
+    }
+
+    fn next(&mut self, now: u64) -> Event {
+        let Some((event, settled_at)) = self.pending[0] else {
+            return self.gestures.update(Event::Nothing, now);
+        };
+
+        match self.gestures.update(Event::Nothing, settled_at) {
+            Event::Nothing => {
+                self.pending = [self.pending[1], None];
+                self.gestures.update(event, settled_at)
+            }
+            due => due,
+        }
+    }
+}
+
+/* Button read by polling its pin */
+pub struct Button<P> {
+    pin: P,
+    polarity: Polarity,
+    state: ButtonState,
+}
+
+impl<P: InputLevel> Button<P> {
+    pub fn new(pin: P, polarity: Polarity) -> Self {
+        Button {
+            pin,
+            polarity,
+            state: ButtonState::new(DEFAULT_DEBOUNCE_MS, GestureConfig::new()),
+        }
+    }
+
+    pub fn with_debounce(mut self, window_ms: u64) -> Self {
+        self.state.debouncer = Debouncer::new(window_ms);
+        self
+    }
+
+    pub fn with_gestures(mut self, config: GestureConfig) -> Self {
+        self.state.gestures = Gestures::new(config);
+        self
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        self.state.is_pressed()
+    }
+
+    /* `now` is a monotonic timestamp in milliseconds, pin errors are passed to the caller */
+    pub fn poll(&mut self, now: u64) -> Result<Event, P::Error> {
+        let pressed_now = self.polarity.is_pressed(self.pin.is_high()?);
+        Ok(self.state.update(pressed_now, now))
+    }
+
+    pub fn release(self) -> P {
+        self.pin
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::Cell;
+    use core::convert::Infallible;
+
+    /* Pin playing back recorded (from ms, high) levels against a fake clock */
+    struct ScriptedPin<'a> {
+        script: &'static [(u64, bool)],
+        clock: &'a Cell<u64>,
+    }
+
+    impl InputLevel for ScriptedPin<'_> {
+        type Error = Infallible;
+
+        fn is_high(&mut self) -> Result<bool, Infallible> {
+            let now = self.clock.get();
+            Ok(self.script.iter().rev().find(|(from, _)| *from <= now).is_none_or(|(_, high)| *high))
+        }
+    }
+
+    /* Polls the button every ms from 0 to `until`, returns the events with their times */
+    fn play(script: &'static [(u64, bool)], until: u64) -> [(u64, Event); 8] {
+        let clock = Cell::new(0);
+        let mut button = Button::new(ScriptedPin { script, clock: &clock }, Polarity::ActiveLow);
+        let mut events = [(0, Event::Nothing); 8];
+        let mut count = 0;
+        for now in 0..=until {
+            clock.set(now);
+            match button.poll(now).unwrap() {
+                Event::Nothing => {}
+                event => {
+                    events[count] = (now, event);
+                    count += 1;
+                }
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn bounces_are_ignored() {
+        /* Active low: contacts bounce for a few ms on press and on release */
+        const SCRIPT: &[(u64, bool)] = &[
+            (100, false), (102, true), (103, false), (105, true), (106, false),
+            (400, true), (401, false), (404, true),
+        ];
+        let events = play(SCRIPT, 1000);
+        assert_eq!(events[0], (136, Event::Pressed));
+        assert_eq!(events[1], (434, Event::Released));
+        assert_eq!(events[2], (0, Event::Nothing));
+    }
+
+    #[test]
+    fn glitch_shorter_than_the_window_is_dropped() {
+        const SCRIPT: &[(u64, bool)] = &[(100, false), (129, true)];
+        assert_eq!(play(SCRIPT, 1000)[0], (0, Event::Nothing));
+    }
+
+    #[test]
+    fn stable_window_boundary() {
+        let mut debouncer = Debouncer::new(30);
+        assert_eq!(debouncer.update(true, 100), Event::Nothing);
+        assert_eq!(debouncer.update(true, 129), Event::Nothing);
+        assert!(!debouncer.is_pressed());
+        /* Exactly `window_ms` after the edge */
+        assert_eq!(debouncer.update(true, 130), Event::Pressed);
+        assert!(debouncer.is_pressed());
+        assert_eq!(debouncer.settled_at(), 130);
+        assert_eq!(debouncer.tick(131), Event::Nothing);
+
+        assert_eq!(debouncer.update(false, 200), Event::Nothing);
+        assert_eq!(debouncer.tick(229), Event::Nothing);
+        assert_eq!(debouncer.tick(230), Event::Released);
+    }
+
+    #[test]
+    fn replayed_edges_settle_at_their_own_time() {
+        let mut debouncer = Debouncer::new(30);
+        /* Edges recorded by an interrupt, fed much later in one go */
+        assert_eq!(debouncer.update(true, 100), Event::Nothing);
+        /* A bounce within the window restarts it */
+        assert_eq!(debouncer.update(false, 110), Event::Nothing);
+        assert_eq!(debouncer.update(true, 112), Event::Nothing);
+        assert_eq!(debouncer.update(false, 500), Event::Pressed);
+        assert_eq!(debouncer.settled_at(), 142);
+        assert_eq!(debouncer.tick(9000), Event::Released);
+        assert_eq!(debouncer.settled_at(), 530);
+    }
+
+    /* Ticks `gestures` every ms in `from..=to`, returns when the first event came */
+    fn first_event(gestures: &mut Gestures, from: u64, to: u64) -> Option<(u64, Event)> {
+        (from..=to).find_map(|now| match gestures.update(Event::Nothing, now) {
+            Event::Nothing => None,
+            event => Some((now, event)),
+        })
+    }
+
+    #[test]
+    fn long_press_exactly_at_the_threshold() {
+        let mut gestures = Gestures::new(GestureConfig::new().long_press(800));
+        assert_eq!(gestures.update(Event::Pressed, 100), Event::Pressed);
+        assert_eq!(first_event(&mut gestures, 101, 899), None);
+        assert_eq!(gestures.update(Event::Nothing, 900), Event::LongPress);
+        /* Once per press */
+        assert_eq!(first_event(&mut gestures, 901, 5000), None);
+        assert_eq!(gestures.update(Event::Released, 5000), Event::Released);
+    }
+
+    #[test]
+    fn repeat_cadence() {
+        let mut gestures = Gestures::new(GestureConfig::new().repeat(500, 150));
+        gestures.update(Event::Pressed, 0);
+        assert_eq!(first_event(&mut gestures, 1, 1000), Some((500, Event::Repeat)));
+        assert_eq!(first_event(&mut gestures, 501, 1000), Some((650, Event::Repeat)));
+        assert_eq!(first_event(&mut gestures, 651, 1000), Some((800, Event::Repeat)));
+
+        /* Main loop busy: one repeat, then the cadence goes on from there */
+        assert_eq!(gestures.update(Event::Nothing, 2000), Event::Repeat);
+        assert_eq!(gestures.update(Event::Nothing, 2001), Event::Nothing);
+        assert_eq!(first_event(&mut gestures, 2002, 3000), Some((2150, Event::Repeat)));
+
+        gestures.update(Event::Released, 2200);
+        assert_eq!(first_event(&mut gestures, 2201, 5000), None);
+    }
+
+    #[test]
+    fn double_click_window_boundary() {
+        let config = GestureConfig::new().double_click(300);
+
+        let mut gestures = Gestures::new(config);
+        gestures.update(Event::Pressed, 0);
+        gestures.update(Event::Released, 100);
+        assert_eq!(gestures.update(Event::Pressed, 400), Event::DoubleClick);
+
+        let mut gestures = Gestures::new(config);
+        gestures.update(Event::Pressed, 0);
+        gestures.update(Event::Released, 100);
+        assert_eq!(gestures.update(Event::Pressed, 401), Event::Pressed);
+
+        /* The second click doesn't start another double click */
+        let mut gestures = Gestures::new(config);
+        gestures.update(Event::Pressed, 0);
+        gestures.update(Event::Released, 100);
+        assert_eq!(gestures.update(Event::Pressed, 200), Event::DoubleClick);
+        gestures.update(Event::Released, 250);
+        assert_eq!(gestures.update(Event::Pressed, 300), Event::Pressed);
+    }
+
+    #[test]
+    fn release_after_long_press_is_no_click() {
+        let mut gestures = Gestures::new(GestureConfig::new().long_press(800).double_click(300));
+        gestures.update(Event::Pressed, 0);
+        assert_eq!(first_event(&mut gestures, 1, 1000), Some((800, Event::LongPress)));
+        assert_eq!(gestures.update(Event::Released, 1000), Event::Released);
+
+        /* Pressing again right away is a new press, not the second click of a double click */
+        assert_eq!(gestures.update(Event::Pressed, 1100), Event::Pressed);
+        assert_eq!(gestures.update(Event::Released, 1150), Event::Released);
+        assert_eq!(gestures.update(Event::Pressed, 1200), Event::DoubleClick);
+    }
+
+    #[test]
+    fn replayed_press_keeps_its_length() {
+        let mut button = ButtonState::new(30, GestureConfig::new().long_press(800));
+
+        /* Held from 1000 to 3000 while the main loop was drawing, both edges replayed at 5000 */
+        assert_eq!(button.update(true, 1000), Event::Nothing);
+        assert_eq!(button.update(false, 3000), Event::Pressed);
+        assert!(!button.has_pending());
+
+        /* The release settled at 3030, the long press was due at 1830 */
+        assert_eq!(button.tick(5000), Event::LongPress);
+        assert!(button.has_pending());
+        assert_eq!(button.tick(5000), Event::Released);
+        assert!(!button.has_pending());
+        assert_eq!(button.tick(5000), Event::Nothing);
+    }
+
+    #[test]
+    fn replayed_short_press_is_not_long() {
+        let mut button = ButtonState::new(30, GestureConfig::new().long_press(800));
+        button.update(true, 1000);
+        assert_eq!(button.update(false, 1200), Event::Pressed);
+        assert_eq!(button.tick(5000), Event::Released);
+        assert_eq!(button.tick(6000), Event::Nothing);
+    }
+}
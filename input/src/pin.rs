@@ -0,0 +1,49 @@
+/* Anything a button level can be read from: MCU pins, pins of an I2C GPIO expander, ...
+ *
+ * Implemented for every embedded-hal 1.0 `InputPin` (feature `eh1`),
+ * embedded-hal 0.2 pins have to be wrapped into `Eh02` (feature `eh02`). */
+pub trait InputLevel {
+    type Error;
+
+    fn is_high(&mut self) -> Result<bool, Self::Error>;
+}
+
+#[cfg(feature = "eh1")]
+impl<P: embedded_hal::digital::InputPin> InputLevel for P {
+    type Error = P::Error;
+
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        embedded_hal::digital::InputPin::is_high(self)
+    }
+}
+
+/* Adapter for pins implementing embedded-hal 0.2 `InputPin` */
+#[cfg(feature = "eh02")]
+pub struct Eh02<P>(pub P);
+
+#[cfg(feature = "eh02")]
+impl<P: embedded_hal_02::digital::v2::InputPin> InputLevel for Eh02<P> {
+    type Error = P::Error;
+
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        self.0.is_high()
+    }
+}
+
+/* How the button is wired */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Polarity {
+    /* Button between the pin and GND, pin configured as pull-up input */
+    ActiveLow,
+    /* Button between the pin and VCC, pin configured as pull-down input */
+    ActiveHigh,
+}
+
+impl Polarity {
+    pub fn is_pressed(self, high: bool) -> bool {
+        match self {
+            Polarity::ActiveLow => !high,
+            Polarity::ActiveHigh => high,
+        }
+    }
+}
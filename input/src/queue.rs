@@ -0,0 +1,73 @@
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/* Raw pin level change captured by the GPIO interrupt */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Edge {
+    pub button: u8,
+    pub pressed: bool,
+    pub timestamp: u64,
+}
+
+impl Edge {
+    const EMPTY: Edge = Edge {
+        button: 0,
+        pressed: false,
+        timestamp: 0,
+    };
+}
+
+/* Lock-free single-producer/single-consumer ring buffer of edges.
+ *
+ * The GPIO interrupt handler is the only producer and the main loop the only consumer.
+ * Only atomic loads and stores are used, so it works on the ESP32-C3 which has no
+ * compare-and-swap. One slot is kept free to tell a full queue from an empty one. */
+pub struct EdgeQueue<const N: usize> {
+    buffer: UnsafeCell<[Edge; N]>,
+    head: AtomicUsize,
+    tail: AtomicUsize,
+}
+
+/* Safe as long as there is at most one producer and one consumer (see above) */
+unsafe impl<const N: usize> Sync for EdgeQueue<N> {}
+
+impl<const N: usize> EdgeQueue<N> {
+    pub const fn new() -> Self {
+        EdgeQueue {
+            buffer: UnsafeCell::new([Edge::EMPTY; N]),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /* Producer side, returns `false` and drops the edge if the queue is full */
+    pub fn push(&self, edge: Edge) -> bool {
+        let head = self.head.load(Ordering::Relaxed);
+        let next = (head + 1) % N;
+        if next == self.tail.load(Ordering::Acquire) {
+            return false;
+        }
+
+        unsafe { (self.buffer.get() as *mut Edge).add(head).write(edge) };
+        self.head.store(next, Ordering::Release);
+        true
+    }
+
+    /* Consumer side */
+    pub fn pop(&self) -> Option<Edge> {
+        let tail = self.tail.load(Ordering::Relaxed);
+        if tail == self.head.load(Ordering::Acquire) {
+            return None;
+        }
+
+        let edge = unsafe { (self.buffer.get() as *const Edge).add(tail).read() };
+        self.tail.store((tail + 1) % N, Ordering::Release);
+        Some(edge)
+    }
+}
+
+impl<const N: usize> Default for EdgeQueue<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
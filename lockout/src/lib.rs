@@ -0,0 +1,182 @@
+/* Brute-force protection for the keypad.
+ *
+ * Pure state machine driven by a monotonic timestamp in milliseconds, it doesn't touch
+ * any hardware. After `max_attempts` wrong codes in a row the keypad is locked for
+ * `base_delay_ms`, every further wrong code doubles the lock time (up to `max_delay_ms`).
+ * A correct code resets the counter. */
+
+#![no_std]
+
+/* Failure counter as kept over a reset (magic, count, inverted count), so that memory holding
+ * garbage after power-on reads as no failures */
+pub type SavedFailures = [u32; 3];
+const SAVED_MAGIC: u32 = 0x4C4F_434B;
+
+pub fn save_failures(count: u32) -> SavedFailures {
+    [SAVED_MAGIC, count, !count]
+}
+
+pub fn saved_failures(saved: SavedFailures) -> u32 {
+    let [magic, count, check] = saved;
+    if magic == SAVED_MAGIC && check == !count {
+        count
+    } else {
+        0
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LockoutPolicy {
+    pub max_attempts: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64,
+}
+
+pub struct Lockout {
+    policy: LockoutPolicy,
+    failures: u32,
+    locked_until: u64,
+}
+
+impl Lockout {
+    pub const fn new(policy: LockoutPolicy) -> Self {
+        Lockout {
+            policy,
+            failures: 0,
+            locked_until: 0,
+        }
+    }
+
+    /* Continues with a failure counter saved before a reset, an active lockout starts over */
+    pub fn restore(policy: LockoutPolicy, failures: u32, now: u64) -> Self {
+        let mut lockout = Lockout::new(policy);
+        lockout.failures = failures;
+        if let Some(delay) = lockout.delay() {
+            lockout.locked_until = now + delay;
+        }
+        lockout
+    }
+
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    pub fn is_locked(&self, now: u64) -> bool {
+        now < self.locked_until
+    }
+
+    pub fn remaining_ms(&self, now: u64) -> u64 {
+        self.locked_until.saturating_sub(now)
+    }
+
+    /* Returns the lock time if this failure locked the keypad */
+    pub fn record_failure(&mut self, now: u64) -> Option<u64> {
+        self.failures = self.failures.saturating_add(1);
+        let delay = self.delay()?;
+        self.locked_until = now + delay;
+        Some(delay)
+    }
+
+    pub fn record_success(&mut self) {
+        self.failures = 0;
+        self.locked_until = 0;
+    }
+
+    fn delay(&self) -> Option<u64> {
+        if self.policy.max_attempts == 0 || self.failures < self.policy.max_attempts {
+            return None;
+        }
+
+        let doublings = (self.failures - self.policy.max_attempts).min(63);
+        let delay = self.policy.base_delay_ms.saturating_mul(1 << doublings);
+        Some(delay.min(self.policy.max_delay_ms))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: LockoutPolicy = LockoutPolicy {
+        max_attempts: 3,
+        base_delay_ms: 1_000,
+        max_delay_ms: 5_000,
+    };
+
+    #[test]
+    fn locks_after_max_attempts_and_doubles() {
+        let mut lockout = Lockout::new(POLICY);
+        assert_eq!(lockout.record_failure(0), None);
+        assert_eq!(lockout.record_failure(10), None);
+        assert!(!lockout.is_locked(10));
+
+        assert_eq!(lockout.record_failure(100), Some(1_000));
+        assert!(lockout.is_locked(100));
+        assert!(lockout.is_locked(1_099));
+        assert!(!lockout.is_locked(1_100));
+        assert_eq!(lockout.remaining_ms(600), 500);
+
+        assert_eq!(lockout.record_failure(2_000), Some(2_000));
+        assert_eq!(lockout.record_failure(5_000), Some(4_000));
+        assert_eq!(lockout.failures(), 5);
+    }
+
+    #[test]
+    fn delay_is_capped() {
+        let mut lockout = Lockout::new(POLICY);
+        for _ in 0..5 {
+            lockout.record_failure(0);
+        }
+        assert_eq!(lockout.record_failure(0), Some(5_000));
+
+        /* Far past the point where the doubling would overflow */
+        let mut lockout = Lockout::restore(POLICY, u32::MAX - 1, 0);
+        assert_eq!(lockout.remaining_ms(0), 5_000);
+        assert_eq!(lockout.record_failure(0), Some(5_000));
+        assert_eq!(lockout.record_failure(0), Some(5_000));
+        assert_eq!(lockout.failures(), u32::MAX);
+
+        let never = LockoutPolicy { max_attempts: 0, ..POLICY };
+        let mut lockout = Lockout::new(never);
+        assert_eq!(lockout.record_failure(0), None);
+    }
+
+    #[test]
+    fn restore_continues_and_relocks() {
+        let lockout = Lockout::restore(POLICY, 2, 50);
+        assert!(!lockout.is_locked(50));
+        assert_eq!(lockout.failures(), 2);
+
+        /* Locked again for the full time after a reset */
+        let lockout = Lockout::restore(POLICY, 4, 50);
+        assert!(lockout.is_locked(2_049));
+        assert!(!lockout.is_locked(2_050));
+    }
+
+    #[test]
+    fn saved_counter_is_checked() {
+        assert_eq!(saved_failures(save_failures(0)), 0);
+        assert_eq!(saved_failures(save_failures(7)), 7);
+
+        let [magic, count, check] = save_failures(7);
+        assert_eq!(saved_failures([magic ^ 1, count, check]), 0);
+        assert_eq!(saved_failures([magic, count, check ^ 1]), 0);
+        assert_eq!(saved_failures([magic, count + 1, check]), 0);
+        assert_eq!(saved_failures([0; 3]), 0);
+        assert_eq!(saved_failures([u32::MAX; 3]), 0);
+
+        /* Bad memory restores an unlocked keypad */
+        let lockout = Lockout::restore(POLICY, saved_failures([0xDEAD_BEEF, 9, 0]), 0);
+        assert!(!lockout.is_locked(0));
+    }
+
+    #[test]
+    fn success_resets() {
+        let mut lockout = Lockout::restore(POLICY, 6, 0);
+        assert!(lockout.is_locked(0));
+        lockout.record_success();
+        assert!(!lockout.is_locked(0));
+        assert_eq!(lockout.failures(), 0);
+        assert_eq!(lockout.record_failure(0), None);
+    }
+}
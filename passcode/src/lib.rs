@@ -0,0 +1,334 @@
+/* Access codes kept as salted hashes instead of plain text.
+ *
+ * The hash is SHA-256 over salt and code, repeated `HASH_ROUNDS` times so that trying all
+ * short numeric codes against a dumped flash takes a while. Up to `MAX_CODES` codes, each
+ * with a role, are stored as one table. Storage is hidden behind `CodeStore`, the keypad
+ * keeps the table in flash, the tests in RAM. */
+
+#![no_std]
+
+use sha2::{Digest, Sha256};
+
+pub const SALT_LEN: usize = 16;
+pub const HASH_LEN: usize = 32;
+const HASH_ROUNDS: u32 = 1024;
+
+pub const MAX_CODES: usize = 8;
+
+/* Erased flash (0xFF..) never matches. "KPC1" was a single code, read as the admin code */
+const MAGIC: [u8; 4] = *b"KPC2";
+const LEGACY_MAGIC: [u8; 4] = *b"KPC1";
+pub const STORED_CODE_LEN: usize = SALT_LEN + HASH_LEN;
+const SLOT_LEN: usize = 1 + STORED_CODE_LEN;
+pub const CODES_LEN: usize = MAGIC.len() + MAX_CODES * SLOT_LEN;
+
+/* Admin code of an empty flash, "12345" (change it in admin mode). Only its salted hash is
+ * in the firmware, with a fixed salt, codes set later get a random one */
+pub const FACTORY_CODE: StoredCode = StoredCode::from_parts(
+    [0x3F, 0xA9, 0x1C, 0x07, 0xD2, 0x5E, 0x8B, 0x64, 0xE0, 0x1D, 0x7A, 0xC3, 0x93, 0x56, 0xF2, 0xB8],
+    [
+        0x6F, 0x19, 0xAB, 0xEA, 0x6C, 0x5C, 0xFB, 0xF9, 0xD7, 0x2E, 0x04, 0xE9, 0x82, 0x53, 0xF8, 0xAA,
+        0x02, 0x58, 0x2E, 0xA6, 0xB1, 0x11, 0xF7, 0x08, 0x06, 0x5A, 0xF0, 0x9A, 0x54, 0x3A, 0x9A, 0x90,
+    ],
+);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    Admin,
+    User,
+    /* One-time code, removed once it was used */
+    Guest,
+}
+
+impl Role {
+    fn to_byte(self) -> u8 {
+        match self {
+            Role::Admin => 0,
+            Role::User => 1,
+            Role::Guest => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(Role::Admin),
+            1 => Some(Role::User),
+            2 => Some(Role::Guest),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct StoredCode {
+    salt: [u8; SALT_LEN],
+    hash: [u8; HASH_LEN],
+}
+
+impl StoredCode {
+    pub fn new(code: &str, salt: [u8; SALT_LEN]) -> Self {
+        StoredCode {
+            salt,
+            hash: hash(code, &salt),
+        }
+    }
+
+    /* Hash computed ahead, see `FACTORY_CODE` */
+    pub const fn from_parts(salt: [u8; SALT_LEN], hash: [u8; HASH_LEN]) -> Self {
+        StoredCode { salt, hash }
+    }
+
+    /* Compares in constant time, so the timing doesn't tell how many bytes matched */
+    pub fn matches(&self, code: &str) -> bool {
+        let candidate = hash(code, &self.salt);
+        let diff = candidate
+            .iter()
+            .zip(self.hash.iter())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b));
+        core::hint::black_box(diff) == 0
+    }
+
+    fn write_to(&self, bytes: &mut [u8]) {
+        bytes[..SALT_LEN].copy_from_slice(&self.salt);
+        bytes[SALT_LEN..STORED_CODE_LEN].copy_from_slice(&self.hash);
+    }
+
+    fn read_from(bytes: &[u8]) -> Self {
+        let mut code = StoredCode {
+            salt: [0; SALT_LEN],
+            hash: [0; HASH_LEN],
+        };
+        code.salt.copy_from_slice(&bytes[..SALT_LEN]);
+        code.hash.copy_from_slice(&bytes[SALT_LEN..STORED_CODE_LEN]);
+        code
+    }
+}
+
+/* Table of codes, a slot index identifies a code (e.g. in the audit log) */
+#[derive(Clone)]
+pub struct Codes {
+    slots: [Option<(Role, StoredCode)>; MAX_CODES],
+}
+
+impl Codes {
+    pub fn new() -> Self {
+        Codes {
+            slots: [None; MAX_CODES],
+        }
+    }
+
+    /* Checks every slot, also after a match, so the time doesn't depend on which slot matched */
+    pub fn find(&self, entered: &str) -> Option<(usize, Role)> {
+        let mut found = None;
+        for (slot, entry) in self.slots.iter().enumerate() {
+            if let Some((role, code)) = entry {
+                if code.matches(entered) && found.is_none() {
+                    found = Some((slot, *role));
+                }
+            }
+        }
+        found
+    }
+
+    pub fn role(&self, slot: usize) -> Option<Role> {
+        self.slots.get(slot)?.map(|(role, _)| role)
+    }
+
+    pub fn set(&mut self, slot: usize, role: Role, code: StoredCode) {
+        self.slots[slot] = Some((role, code));
+    }
+
+    /* Puts the code into the first free slot, `None` if the table is full */
+    pub fn add(&mut self, role: Role, code: StoredCode) -> Option<usize> {
+        let slot = self.slots.iter().position(Option::is_none)?;
+        self.set(slot, role, code);
+        Some(slot)
+    }
+
+    pub fn remove(&mut self, slot: usize) {
+        self.slots[slot] = None;
+    }
+
+    /* Removes all user and guest codes */
+    pub fn remove_non_admins(&mut self) {
+        for entry in self.slots.iter_mut() {
+            if !matches!(entry, Some((Role::Admin, _))) {
+                *entry = None;
+            }
+        }
+    }
+
+    pub fn to_bytes(&self) -> [u8; CODES_LEN] {
+        let mut bytes = [0xFFu8; CODES_LEN];
+        bytes[..4].copy_from_slice(&MAGIC);
+        for (entry, slot) in self.slots.iter().zip(bytes[4..].chunks_mut(SLOT_LEN)) {
+            if let Some((role, code)) = entry {
+                slot[0] = role.to_byte();
+                code.write_to(&mut slot[1..]);
+            }
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; CODES_LEN]) -> Option<Self> {
+        let mut codes = Codes::new();
+
+        if bytes[..4] == LEGACY_MAGIC {
+            codes.set(0, Role::Admin, StoredCode::read_from(&bytes[4..]));
+            return Some(codes);
+        }
+        if bytes[..4] != MAGIC {
+            return None;
+        }
+
+        for (entry, slot) in codes.slots.iter_mut().zip(bytes[4..].chunks(SLOT_LEN)) {
+            *entry = Role::from_byte(slot[0]).map(|role| (role, StoredCode::read_from(&slot[1..])));
+        }
+        Some(codes)
+    }
+}
+
+impl Default for Codes {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn hash(code: &str, salt: &[u8; SALT_LEN]) -> [u8; HASH_LEN] {
+    let mut digest: [u8; HASH_LEN] = Sha256::new()
+        .chain_update(salt)
+        .chain_update(code.as_bytes())
+        .finalize()
+        .into();
+
+    for _ in 1..HASH_ROUNDS {
+        digest = Sha256::new()
+            .chain_update(salt)
+            .chain_update(digest)
+            .finalize()
+            .into();
+    }
+    digest
+}
+
+pub trait CodeStore {
+    type Error;
+
+    /* `None` if no codes were stored yet */
+    fn load(&mut self) -> Result<Option<Codes>, Self::Error>;
+
+    fn save(&mut self, codes: &Codes) -> Result<(), Self::Error>;
+}
+
+/* Keeps the codes in RAM only, for the tests */
+#[cfg(test)]
+#[derive(Default)]
+pub struct MemoryStore {
+    bytes: Option<[u8; CODES_LEN]>,
+}
+
+#[cfg(test)]
+impl CodeStore for MemoryStore {
+    type Error = core::convert::Infallible;
+
+    fn load(&mut self) -> Result<Option<Codes>, Self::Error> {
+        Ok(self.bytes.as_ref().and_then(Codes::from_bytes))
+    }
+
+    fn save(&mut self, codes: &Codes) -> Result<(), Self::Error> {
+        self.bytes = Some(codes.to_bytes());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SALT: [u8; SALT_LEN] = [7; SALT_LEN];
+
+    #[test]
+    fn stored_code_matches_only_its_code() {
+        let code = StoredCode::new("2468", SALT);
+        assert!(code.matches("2468"));
+        assert!(!code.matches("2469"));
+        assert!(!code.matches("24680"));
+        assert!(!code.matches(""));
+
+        /* Same code, other salt, other hash */
+        assert!(StoredCode::new("2468", [8; SALT_LEN]) != code);
+    }
+
+    #[test]
+    fn factory_code_is_12345() {
+        assert!(FACTORY_CODE.matches("12345"));
+        assert!(!FACTORY_CODE.matches("1234"));
+    }
+
+    #[test]
+    fn codes_survive_the_store() {
+        let mut store = MemoryStore::default();
+        assert!(store.load().unwrap().is_none());
+
+        let mut codes = Codes::new();
+        codes.add(Role::Admin, StoredCode::new("1111", SALT));
+        let guest = codes.add(Role::Guest, StoredCode::new("2222", SALT)).unwrap();
+        codes.add(Role::User, StoredCode::new("3333", SALT));
+        codes.remove(guest);
+        store.save(&codes).unwrap();
+
+        let loaded = store.load().unwrap().unwrap();
+        assert_eq!(loaded.find("1111"), Some((0, Role::Admin)));
+        assert_eq!(loaded.find("2222"), None);
+        assert_eq!(loaded.find("3333"), Some((2, Role::User)));
+        assert_eq!(loaded.role(guest), None);
+    }
+
+    #[test]
+    fn legacy_single_code_becomes_admin() {
+        let mut bytes = [0xFFu8; CODES_LEN];
+        bytes[..4].copy_from_slice(&LEGACY_MAGIC);
+        StoredCode::new("9876", SALT).write_to(&mut bytes[4..]);
+
+        let codes = Codes::from_bytes(&bytes).unwrap();
+        assert_eq!(codes.find("9876"), Some((0, Role::Admin)));
+        assert_eq!((1..MAX_CODES).filter_map(|slot| codes.role(slot)).count(), 0);
+
+        /* Saved again in the current format */
+        let mut store = MemoryStore::default();
+        store.save(&codes).unwrap();
+        assert_eq!(store.bytes.unwrap()[..4], MAGIC);
+        assert_eq!(store.load().unwrap().unwrap().find("9876"), Some((0, Role::Admin)));
+    }
+
+    #[test]
+    fn table_fills_slots_in_order() {
+        let mut codes = Codes::new();
+        assert_eq!(codes.add(Role::Admin, StoredCode::new("1000", SALT)), Some(0));
+        for slot in 1..MAX_CODES {
+            let role = if slot % 2 == 0 { Role::Guest } else { Role::User };
+            assert_eq!(codes.add(role, StoredCode::new("2000", SALT)), Some(slot));
+        }
+        assert_eq!(codes.add(Role::User, StoredCode::new("3000", SALT)), None);
+
+        /* The same code in several slots is found in the first one */
+        assert_eq!(codes.find("2000"), Some((1, Role::User)));
+        assert_eq!(codes.role(2), Some(Role::Guest));
+        assert_eq!(codes.role(MAX_CODES), None);
+
+        codes.remove(3);
+        assert_eq!(codes.add(Role::Guest, StoredCode::new("4000", SALT)), Some(3));
+        assert_eq!(codes.find("4000"), Some((3, Role::Guest)));
+
+        codes.remove_non_admins();
+        assert_eq!(codes.find("1000"), Some((0, Role::Admin)));
+        assert_eq!(codes.find("2000"), None);
+        assert_eq!((1..MAX_CODES).filter_map(|slot| codes.role(slot)).count(), 0);
+    }
+
+    #[test]
+    fn erased_flash_has_no_codes() {
+        assert!(Codes::from_bytes(&[0xFF; CODES_LEN]).is_none());
+        assert!(Codes::from_bytes(&[0; CODES_LEN]).is_none());
+    }
+}
@@ -0,0 +1,592 @@
+/* Converts SVG pictures into drawing commands for the gallery's vector artworks, at build
+ * time (the gallery's build.rs writes them out as const tables).
+ *
+ * Only a subset of SVG is understood, anything else is an error rather than a silently
+ * wrong picture:
+ *   <path d="..">       M L H V C Q Z (absolute and relative), curves are flattened to lines
+ *   <line>, <polyline>, <polygon>, <rect>, <circle>
+ *   <rose cx cy r n [d]> polar curve r * sin(n * angle), not SVG: a rose, or with `d` (degrees
+ *                        between points) a Maurer rose drawn as dots
+ *   <g>                 only to share stroke and stroke-width with the elements inside
+ *   <title>             name shown in the menu, the file name otherwise
+ *   <desc>, <metadata>  skipped with everything inside
+ * Only strokes are drawn (`stroke` as #rgb/#rrggbb/black/white/none, `stroke-width`), fills
+ * are ignored and a `transform` is an error. The viewBox is scaled to fit the 320x240 screen,
+ * centered.
+ *
+ * Commands come out as the source of `vector::Command` values, e.g. `MoveTo(10, 20)`. */
+
+use std::{collections::HashMap, path::Path};
+
+const SCREEN: (f64, f64) = (320.0, 240.0);
+/* Line segments a Bezier curve is flattened to */
+const CURVE_SEGMENTS: u32 = 12;
+
+type Attributes = HashMap<String, String>;
+
+struct Tag {
+    name: String,
+    attributes: Attributes,
+    closing: bool,
+    self_closing: bool,
+    /* Text up to the next tag */
+    text: String,
+}
+
+/* Maps viewBox coordinates to the screen */
+struct View {
+    scale: f64,
+    offset: (f64, f64),
+}
+
+impl View {
+    fn point(&self, (x, y): (f64, f64)) -> String {
+        let x = (x * self.scale + self.offset.0).round() as i16;
+        let y = (y * self.scale + self.offset.1).round() as i16;
+        format!("({}, {})", x, y)
+    }
+}
+
+#[derive(Clone)]
+struct Style {
+    stroke: Option<(u8, u8, u8)>,
+    width: f64,
+}
+
+/* Name of the artwork and its commands */
+pub fn convert(file: &Path, source: &str) -> Result<(String, Vec<String>), String> {
+    let tags = parse_tags(source)?;
+
+    let mut name = file.file_stem().unwrap().to_string_lossy().replace(['_', '-'], " ");
+    let mut view = None;
+    let mut styles = vec![Style { stroke: Some((0, 0, 0)), width: 1.0 }];
+    let mut current_stroke = None;
+    let mut commands = Vec::new();
+    /* Inside a <desc> or <metadata>: its name and how deep in nested ones of the same name */
+    let mut skipped: Option<(&str, u32)> = None;
+
+    for tag in &tags {
+        if let Some((skipped_name, depth)) = skipped.as_mut() {
+            if tag.name == *skipped_name && !tag.self_closing {
+                *depth = if tag.closing { *depth - 1 } else { *depth + 1 };
+                if *depth == 0 {
+                    skipped = None;
+                }
+            }
+            continue;
+        }
+
+        if tag.closing {
+            if tag.name == "g" {
+                styles.pop();
+            }
+            continue;
+        }
+
+        if tag.attributes.contains_key("transform") {
+            return Err(format!("transform on <{}> isn't supported", tag.name));
+        }
+        let style = inherit(styles.last().unwrap(), &tag.attributes)?;
+        match tag.name.as_str() {
+            "svg" => view = Some(view_box(&tag.attributes)?),
+            "title" => name = tag.text.trim().to_string(),
+            "g" if !tag.self_closing => styles.push(style),
+            "g" => {}
+            "desc" | "metadata" if !tag.self_closing => skipped = Some((tag.name.as_str(), 1)),
+            "desc" | "metadata" => {}
+            "path" | "line" | "polyline" | "polygon" | "rect" | "circle" | "rose" => {
+                let view = view.as_ref().ok_or("drawing before <svg>")?;
+                let stroke = match style.stroke {
+                    Some(stroke) => stroke,
+                    None => continue,
+                };
+                let width = (style.width * view.scale).round().max(1.0) as u32;
+                if current_stroke != Some((width, stroke)) {
+                    commands.push(format!("Stroke({}, Rgb565::new({}, {}, {}))", width, stroke.0 >> 3, stroke.1 >> 2, stroke.2 >> 3));
+                    current_stroke = Some((width, stroke));
+                }
+                shape(&tag.name, &tag.attributes, view, &mut commands)?;
+            }
+            _ => return Err(format!("unsupported element <{}>", tag.name)),
+        }
+    }
+
+    Ok((name, commands))
+}
+
+fn shape(name: &str, attributes: &Attributes, view: &View, commands: &mut Vec<String>) -> Result<(), String> {
+    let number = |key: &str| number(attributes, key);
+    match name {
+        "path" => path(attributes.get("d").ok_or("<path> without d")?, view, commands)?,
+        "line" => {
+            commands.push(format!("MoveTo{}", view.point((number("x1")?, number("y1")?))));
+            commands.push(format!("LineTo{}", view.point((number("x2")?, number("y2")?))));
+        }
+        "polyline" | "polygon" => {
+            let values = numbers(attributes.get("points").ok_or("without points")?)?;
+            let points: Vec<(f64, f64)> = values.chunks(2).map(|pair| (pair[0], pair[1])).collect();
+            polyline(&points, name == "polygon", view, commands);
+        }
+        "rect" => {
+            let (x, y) = (number("x").unwrap_or(0.0), number("y").unwrap_or(0.0));
+            let (width, height) = (number("width")?, number("height")?);
+            polyline(&[(x, y), (x + width, y), (x + width, y + height), (x, y + height)], true, view, commands);
+        }
+        "circle" => {
+            let radius = (number("r")? * view.scale).round() as u16;
+            commands.push(format!("Circle {{ center: {}, radius: {} }}", view.point((number("cx")?, number("cy")?)), radius));
+        }
+        "rose" => {
+            let radius = (number("r")? * view.scale).round() as u16;
+            let center = view.point((number("cx")?, number("cy")?));
+            let d = match attributes.get("d") {
+                Some(_) => format!("Some({:?})", number("d")? as f32),
+                None => "None".to_string(),
+            };
+            commands.push(format!("Rose {{ center: {}, radius: {}, n: {:?}, d: {} }}", center, radius, number("n")? as f32, d));
+        }
+        _ => unreachable!(),
+    }
+    Ok(())
+}
+
+fn polyline(points: &[(f64, f64)], closed: bool, view: &View, commands: &mut Vec<String>) {
+    for (i, point) in points.iter().enumerate() {
+        let command = if i == 0 { "MoveTo" } else { "LineTo" };
+        commands.push(format!("{}{}", command, view.point(*point)));
+    }
+    if closed && !points.is_empty() {
+        commands.push(format!("LineTo{}", view.point(points[0])));
+    }
+}
+
+fn path(d: &str, view: &View, commands: &mut Vec<String>) -> Result<(), String> {
+    let tokens = path_tokens(d)?;
+    let mut args = Arguments { tokens: &tokens, next: 0 };
+    let mut command = None;
+    let mut current = (0.0, 0.0);
+    let mut start = (0.0, 0.0);
+
+    while args.next < tokens.len() {
+        let letter = match tokens[args.next] {
+            PathToken::Command(letter) => {
+                args.next += 1;
+                letter
+            }
+            /* More coordinates: the previous command again, a moveto continues as lineto */
+            PathToken::Number(_) => match command {
+                Some('M') => 'L',
+                Some('m') => 'l',
+                Some('Z' | 'z') => return Err("coordinates after closepath".into()),
+                Some(letter) => letter,
+                None => return Err("path data has to start with a command".into()),
+            },
+        };
+        command = Some(letter);
+
+        let relative = letter.is_ascii_lowercase();
+        let base = if relative { current } else { (0.0, 0.0) };
+
+        match letter.to_ascii_uppercase() {
+            'M' => {
+                current = args.point(base)?;
+                start = current;
+                commands.push(format!("MoveTo{}", view.point(current)));
+            }
+            'L' => {
+                current = args.point(base)?;
+                commands.push(format!("LineTo{}", view.point(current)));
+            }
+            'H' => {
+                current.0 = base.0 + args.number()?;
+                commands.push(format!("LineTo{}", view.point(current)));
+            }
+            'V' => {
+                current.1 = base.1 + args.number()?;
+                commands.push(format!("LineTo{}", view.point(current)));
+            }
+            'Q' => {
+                let (control, end) = (args.point(base)?, args.point(base)?);
+                flatten(&[current, control, end], view, commands);
+                current = end;
+            }
+            'C' => {
+                let (first, second, end) = (args.point(base)?, args.point(base)?, args.point(base)?);
+                flatten(&[current, first, second, end], view, commands);
+                current = end;
+            }
+            'Z' => {
+                current = start;
+                commands.push(format!("LineTo{}", view.point(current)));
+            }
+            _ => return Err(format!("unsupported path command '{}'", letter)),
+        }
+    }
+    Ok(())
+}
+
+struct Arguments<'a> {
+    tokens: &'a [PathToken],
+    next: usize,
+}
+
+impl Arguments<'_> {
+    fn number(&mut self) -> Result<f64, String> {
+        match self.tokens.get(self.next) {
+            Some(PathToken::Number(value)) => {
+                self.next += 1;
+                Ok(*value)
+            }
+            _ => Err("missing path command argument".into()),
+        }
+    }
+
+    fn point(&mut self, base: (f64, f64)) -> Result<(f64, f64), String> {
+        Ok((base.0 + self.number()?, base.1 + self.number()?))
+    }
+}
+
+fn flatten(points: &[(f64, f64)], view: &View, commands: &mut Vec<String>) {
+    for i in 1..=CURVE_SEGMENTS {
+        let point = bezier(points, i as f64 / CURVE_SEGMENTS as f64);
+        commands.push(format!("LineTo{}", view.point(point)));
+    }
+}
+
+/* De Casteljau */
+fn bezier(points: &[(f64, f64)], t: f64) -> (f64, f64) {
+    let mut points = points.to_vec();
+    while points.len() > 1 {
+        points = points
+            .windows(2)
+            .map(|pair| (pair[0].0 + (pair[1].0 - pair[0].0) * t, pair[0].1 + (pair[1].1 - pair[0].1) * t))
+            .collect();
+    }
+    points[0]
+}
+
+#[derive(Clone, Copy)]
+enum PathToken {
+    Command(char),
+    Number(f64),
+}
+
+fn path_tokens(d: &str) -> Result<Vec<PathToken>, String> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = d.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() || c == ',' {
+            i += 1;
+        } else if c.is_ascii_alphabetic() && c != 'e' && c != 'E' {
+            tokens.push(PathToken::Command(c));
+            i += 1;
+        } else {
+            /* Numbers may follow each other without a separator: "1-2", "0.5.5" */
+            let begin = i;
+            let mut seen_dot = false;
+            let mut seen_exponent = false;
+            i += 1;
+            if c == '.' {
+                seen_dot = true;
+            }
+            while i < chars.len() {
+                match chars[i] {
+                    '0'..='9' => {}
+                    '.' if !seen_dot && !seen_exponent => seen_dot = true,
+                    'e' | 'E' if !seen_exponent => seen_exponent = true,
+                    '-' | '+' if matches!(chars[i - 1], 'e' | 'E') => {}
+                    _ => break,
+                }
+                i += 1;
+            }
+            let text: String = chars[begin..i].iter().collect();
+            let value = text.parse().map_err(|_| format!("bad number '{}' in path", text))?;
+            tokens.push(PathToken::Number(value));
+        }
+    }
+    Ok(tokens)
+}
+
+fn view_box(attributes: &Attributes) -> Result<View, String> {
+    let (min, size) = match attributes.get("viewBox") {
+        Some(view_box) => {
+            let values = numbers(view_box)?;
+            if values.len() != 4 {
+                return Err("viewBox needs 4 numbers".into());
+            }
+            ((values[0], values[1]), (values[2], values[3]))
+        }
+        None => ((0.0, 0.0), (number(attributes, "width")?, number(attributes, "height")?)),
+    };
+
+    let scale = (SCREEN.0 / size.0).min(SCREEN.1 / size.1);
+    Ok(View {
+        scale,
+        offset: (
+            (SCREEN.0 - size.0 * scale) / 2.0 - min.0 * scale,
+            (SCREEN.1 - size.1 * scale) / 2.0 - min.1 * scale,
+        ),
+    })
+}
+
+fn inherit(parent: &Style, attributes: &Attributes) -> Result<Style, String> {
+    let mut style = parent.clone();
+    if let Some(stroke) = attributes.get("stroke") {
+        style.stroke = color(stroke)?;
+    }
+    if attributes.contains_key("stroke-width") {
+        style.width = number(attributes, "stroke-width")?;
+    }
+    Ok(style)
+}
+
+fn color(value: &str) -> Result<Option<(u8, u8, u8)>, String> {
+    let hex = |digits: &str| u8::from_str_radix(digits, 16).map_err(|_| format!("bad color '{}'", value));
+    match value.trim() {
+        "none" => Ok(None),
+        "black" => Ok(Some((0, 0, 0))),
+        "white" => Ok(Some((255, 255, 255))),
+        v if v.len() == 7 && v.starts_with('#') => Ok(Some((hex(&v[1..3])?, hex(&v[3..5])?, hex(&v[5..7])?))),
+        v if v.len() == 4 && v.starts_with('#') => {
+            let short = |i: usize| hex(&v[i..i + 1]).map(|c| c * 17);
+            Ok(Some((short(1)?, short(2)?, short(3)?)))
+        }
+        _ => Err(format!("unsupported color '{}'", value)),
+    }
+}
+
+fn number(attributes: &Attributes, key: &str) -> Result<f64, String> {
+    let value = attributes.get(key).ok_or(format!("missing attribute '{}'", key))?;
+    value
+        .trim()
+        .trim_end_matches("px")
+        .parse()
+        .map_err(|_| format!("attribute '{}' isn't a number: '{}'", key, value))
+}
+
+fn numbers(value: &str) -> Result<Vec<f64>, String> {
+    value
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse().map_err(|_| format!("'{}' isn't a number", part)))
+        .collect()
+}
+
+/* Just enough XML: tags with quoted attributes, text, comments and declarations skipped */
+fn parse_tags(source: &str) -> Result<Vec<Tag>, String> {
+    let mut tags = Vec::new();
+    let mut rest = source;
+
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start..];
+        if rest.starts_with("<!--") {
+            let end = rest.find("-->").ok_or("unterminated comment")?;
+            rest = &rest[end + 3..];
+            continue;
+        }
+        if rest.starts_with("<?") || rest.starts_with("<!") {
+            let end = rest.find('>').ok_or("unterminated declaration")?;
+            rest = &rest[end + 1..];
+            continue;
+        }
+
+        let end = tag_end(rest).ok_or("unterminated tag")?;
+        let inner = &rest[1..end];
+        rest = &rest[end + 1..];
+        let text = rest[..rest.find('<').unwrap_or(rest.len())].to_string();
+
+        let closing = inner.starts_with('/');
+        let self_closing = inner.ends_with('/');
+        let inner = inner.trim_start_matches('/').trim_end_matches('/');
+        let name_end = inner.find(|c: char| c.is_whitespace()).unwrap_or(inner.len());
+
+        tags.push(Tag {
+            name: inner[..name_end].to_string(),
+            attributes: attributes(&inner[name_end..])?,
+            closing,
+            self_closing,
+            text,
+        });
+    }
+    Ok(tags)
+}
+
+/* Position of the '>' closing the tag, ignoring any inside quoted values */
+fn tag_end(tag: &str) -> Option<usize> {
+    let mut quote = None;
+    for (i, c) in tag.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '>') => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn attributes(mut text: &str) -> Result<Attributes, String> {
+    let mut attributes = Attributes::new();
+    loop {
+        text = text.trim_start();
+        if text.is_empty() {
+            return Ok(attributes);
+        }
+        let eq = text.find('=').ok_or_else(|| format!("attribute without value: '{}'", text))?;
+        let key = text[..eq].trim().to_string();
+        text = text[eq + 1..].trim_start();
+        let quote = text.chars().next().filter(|c| *c == '"' || *c == '\'').ok_or("unquoted attribute value")?;
+        let end = text[1..].find(quote).ok_or("unterminated attribute value")? + 1;
+        attributes.insert(key, text[1..end].to_string());
+        text = &text[end + 1..];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: &str = "Stroke(1, Rgb565::new(0, 0, 0))";
+
+    /* Drawn on a viewBox the size of the screen, so coordinates come out unchanged */
+    fn commands(body: &str) -> Result<Vec<String>, String> {
+        let source = format!(r#"<svg viewBox="0 0 320 240">{}</svg>"#, body);
+        convert(Path::new("assets/test_art.svg"), &source).map(|(_, commands)| commands)
+    }
+
+    fn values(tokens: &[PathToken]) -> Vec<String> {
+        tokens
+            .iter()
+            .map(|token| match token {
+                PathToken::Command(letter) => letter.to_string(),
+                PathToken::Number(value) => value.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn numbers_without_separators() {
+        let tokens = path_tokens("M10-20L.5.5e1,1E-1 -3").unwrap();
+        assert_eq!(values(&tokens), ["M", "10", "-20", "L", "0.5", "5", "0.1", "-3"]);
+        assert!(path_tokens("M 1 2 L 3 -").is_err());
+    }
+
+    #[test]
+    fn absolute_and_relative_path_commands() {
+        let commands = commands(r#"<path d="M10 10 l5 0 h5 v-5 H0 V0 m1 1 2 2 z"/>"#).unwrap();
+        assert_eq!(
+            commands,
+            [
+                BLACK,
+                "MoveTo(10, 10)",
+                "LineTo(15, 10)",
+                "LineTo(20, 10)",
+                "LineTo(20, 5)",
+                "LineTo(0, 5)",
+                "LineTo(0, 0)",
+                /* Coordinates after a moveto are linetos */
+                "MoveTo(1, 1)",
+                "LineTo(3, 3)",
+                "LineTo(1, 1)",
+            ]
+        );
+    }
+
+    #[test]
+    fn curves_are_flattened_to_their_end_point() {
+        let commands = commands(r#"<path d="M0 0 Q10 0 10 10 c0 10 10 10 10 0"/>"#).unwrap();
+        assert_eq!(commands.len(), 2 + 2 * CURVE_SEGMENTS as usize);
+        assert_eq!(commands[1 + CURVE_SEGMENTS as usize], "LineTo(10, 10)");
+        assert_eq!(commands.last().unwrap(), "LineTo(20, 10)");
+        assert_eq!(bezier(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)], 0.5), (7.5, 2.5));
+    }
+
+    #[test]
+    fn bad_path_data() {
+        assert!(commands(r#"<path d="10 10"/>"#).unwrap_err().contains("start with a command"));
+        assert!(commands(r#"<path d="M0 0 A5 5 0 0 1 10 10"/>"#).unwrap_err().contains("'A'"));
+        assert!(commands(r#"<path d="M0 0 L5"/>"#).unwrap_err().contains("missing"));
+        assert!(commands(r#"<path d="M0 0 L5 5 Z 1 1"/>"#).unwrap_err().contains("closepath"));
+        assert!(commands("<path/>").unwrap_err().contains("without d"));
+    }
+
+    #[test]
+    fn shapes() {
+        let commands = commands(
+            r#"<line x1="1" y1="2" x2="3" y2="4"/>
+               <polygon points="0,0 4,0 4,4"/>
+               <rect x="10" y="20" width="5" height="6"/>
+               <circle cx="50" cy="60" r="7"/>
+               <rose cx="100" cy="100" r="40" n="6" d="71"/>"#,
+        )
+        .unwrap();
+        assert_eq!(
+            commands,
+            [
+                BLACK,
+                "MoveTo(1, 2)",
+                "LineTo(3, 4)",
+                "MoveTo(0, 0)",
+                "LineTo(4, 0)",
+                "LineTo(4, 4)",
+                "LineTo(0, 0)",
+                "MoveTo(10, 20)",
+                "LineTo(15, 20)",
+                "LineTo(15, 26)",
+                "LineTo(10, 26)",
+                "LineTo(10, 20)",
+                "Circle { center: (50, 60), radius: 7 }",
+                "Rose { center: (100, 100), radius: 40, n: 6.0, d: Some(71.0) }",
+            ]
+        );
+    }
+
+    #[test]
+    fn view_box_is_scaled_and_centered() {
+        let source = r#"<svg viewBox="-10 0 100 100"><line x1="-10" y1="0" x2="90" y2="100" stroke-width="2"/></svg>"#;
+        let (name, commands) = convert(Path::new("assets/two_words.svg"), source).unwrap();
+        assert_eq!(name, "two words");
+        /* 2.4 times, 40 pixels left and right */
+        assert_eq!(commands, ["Stroke(5, Rgb565::new(0, 0, 0))", "MoveTo(40, 0)", "LineTo(280, 240)"]);
+    }
+
+    #[test]
+    fn groups_share_their_stroke() {
+        let commands = commands(
+            r##"<title> Boat </title>
+                <g stroke="#f00"><line x1="0" y1="0" x2="1" y2="1"/><line x1="0" y1="0" x2="1" y2="1" stroke="none"/></g>
+                <line x1="0" y1="0" x2="1" y2="1"/>"##,
+        )
+        .unwrap();
+        assert_eq!(
+            commands,
+            ["Stroke(1, Rgb565::new(31, 0, 0))", "MoveTo(0, 0)", "LineTo(1, 1)", BLACK, "MoveTo(0, 0)", "LineTo(1, 1)"]
+        );
+    }
+
+    #[test]
+    fn description_and_metadata_are_skipped() {
+        let commands = commands(
+            r#"<desc>A <b>bold</b> picture</desc>
+               <metadata><rdf:RDF><metadata/><cc:Work/></rdf:RDF></metadata>
+               <line x1="0" y1="0" x2="1" y2="1"/>"#,
+        )
+        .unwrap();
+        assert_eq!(commands, [BLACK, "MoveTo(0, 0)", "LineTo(1, 1)"]);
+    }
+
+    #[test]
+    fn anything_else_is_an_error() {
+        assert_eq!(commands(r#"<ellipse cx="1" cy="1" rx="2" ry="3"/>"#).unwrap_err(), "unsupported element <ellipse>");
+        assert_eq!(commands("<text>Hi</text>").unwrap_err(), "unsupported element <text>");
+        assert_eq!(commands(r##"<use href="#a"/>"##).unwrap_err(), "unsupported element <use>");
+        assert!(commands(r#"<g transform="scale(2)"><line x1="0" y1="0" x2="1" y2="1"/></g>"#)
+            .unwrap_err()
+            .contains("transform"));
+        assert!(commands(r#"<line x1="0" y1="0" x2="1" y2="1" transform="rotate(5)"/>"#).is_err());
+        assert!(commands(r#"<line x1="0" y1="0" x2="1" y2="1" stroke="red"/>"#).unwrap_err().contains("color"));
+        assert!(convert(Path::new("a.svg"), r#"<line x1="0" y1="0" x2="1" y2="1"/>"#).unwrap_err().contains("before <svg>"));
+    }
+}
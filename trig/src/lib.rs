@@ -0,0 +1,236 @@
+/* Fixed-point sine and cosine for chips without an FPU (ESP32-C3).
+ *
+ * Angles are binary angles: a `u32` where 2^32 is a full turn, so wrapping around is free.
+ * Results are Q16.16 (`ONE` = 1.0). The sine comes from a quarter-wave table with linear
+ * interpolation in between, the error stays below one step of Q16.16 (1.5e-5).
+ * `Fixed` does the arithmetic around it in Q16.16, so a curve can go from the angle to the
+ * point without floating point. `radians` has drop-in replacements for `libm::sin`/`cos` on
+ * `f64` built on top of it, they convert on every call. */
+
+#![no_std]
+
+use core::ops::{Add, Mul, Neg, Sub};
+
+pub type Angle = u32;
+
+pub const ONE: i32 = 1 << 16;
+pub const QUARTER_TURN: Angle = 1 << 30;
+
+/* sin(i / 256 * PI / 2) in Q2.30, interpolated at this precision and rounded to Q16.16 after */
+const QUARTER_SINE: [i32; 257] = [
+    0, 6588356, 13176464, 19764076, 26350943, 32936819,
+    39521455, 46104602, 52686014, 59265442, 65842639, 72417357,
+    78989349, 85558366, 92124163, 98686491, 105245103, 111799753,
+    118350194, 124896179, 131437462, 137973796, 144504935, 151030634,
+    157550647, 164064728, 170572633, 177074115, 183568930, 190056834,
+    196537583, 203010932, 209476638, 215934457, 222384147, 228825464,
+    235258165, 241682010, 248096755, 254502159, 260897982, 267283981,
+    273659918, 280025552, 286380643, 292724951, 299058239, 305380268,
+    311690799, 317989595, 324276419, 330551034, 336813204, 343062693,
+    349299266, 355522689, 361732726, 367929144, 374111709, 380280190,
+    386434353, 392573967, 398698801, 404808624, 410903207, 416982319,
+    423045732, 429093217, 435124548, 441139496, 447137835, 453119340,
+    459083786, 465030947, 470960600, 476872522, 482766489, 488642281,
+    494499676, 500338453, 506158392, 511959275, 517740883, 523502998,
+    529245404, 534967884, 540670223, 546352205, 552013618, 557654248,
+    563273883, 568872310, 574449320, 580004702, 585538248, 591049748,
+    596538995, 602005783, 607449906, 612871159, 618269338, 623644239,
+    628995660, 634323400, 639627258, 644907034, 650162530, 655393548,
+    660599890, 665781362, 670937767, 676068911, 681174602, 686254647,
+    691308855, 696337036, 701339000, 706314559, 711263525, 716185713,
+    721080937, 725949013, 730789757, 735602987, 740388522, 745146182,
+    749875788, 754577161, 759250125, 763894504, 768510122, 773096806,
+    777654384, 782182683, 786681534, 791150767, 795590213, 799999706,
+    804379079, 808728167, 813046808, 817334838, 821592095, 825818421,
+    830013654, 834177638, 838310216, 842411232, 846480531, 850517961,
+    854523370, 858496606, 862437520, 866345964, 870221790, 874064853,
+    877875009, 881652112, 885396022, 889106597, 892783698, 896427186,
+    900036924, 903612776, 907154608, 910662286, 914135678, 917574653,
+    920979082, 924348837, 927683790, 930983817, 934248793, 937478595,
+    940673101, 943832191, 946955747, 950043650, 953095785, 956112036,
+    959092290, 962036435, 964944360, 967815955, 970651112, 973449725,
+    976211688, 978936898, 981625251, 984276646, 986890984, 989468165,
+    992008094, 994510675, 996975812, 999403415, 1001793390, 1004145648,
+    1006460100, 1008736660, 1010975242, 1013175761, 1015338134, 1017462281,
+    1019548121, 1021595575, 1023604567, 1025575020, 1027506862, 1029400018,
+    1031254418, 1033069992, 1034846671, 1036584389, 1038283080, 1039942680,
+    1041563127, 1043144360, 1044686319, 1046188946, 1047652185, 1049075980,
+    1050460278, 1051805027, 1053110176, 1054375676, 1055601479, 1056787540,
+    1057933813, 1059040255, 1060106826, 1061133483, 1062120190, 1063066909,
+    1063973603, 1064840240, 1065666786, 1066453210, 1067199483, 1067905576,
+    1068571464, 1069197120, 1069782521, 1070327646, 1070832474, 1071296985,
+    1071721163, 1072104991, 1072448455, 1072751542, 1073014240, 1073236540,
+    1073418433, 1073559913, 1073660973, 1073721611, 1073741824,
+];
+
+/* Angle bits: 2 for the quadrant, 8 for the table entry, 16 of the rest for interpolation */
+const INDEX_SHIFT: u32 = 22;
+const FRACTION_SHIFT: u32 = 6;
+
+pub fn sin(angle: Angle) -> i32 {
+    let quadrant = angle >> 30;
+    let index = ((angle >> INDEX_SHIFT) & 0xFF) as usize;
+    let fraction = ((angle >> FRACTION_SHIFT) & 0xFFFF) as i64;
+
+    /* Odd quadrants run the table backwards */
+    let (from, to) = if quadrant & 1 == 0 {
+        (QUARTER_SINE[index], QUARTER_SINE[index + 1])
+    } else {
+        (QUARTER_SINE[256 - index], QUARTER_SINE[255 - index])
+    };
+    let value = from + (((to - from) as i64 * fraction) >> 16) as i32;
+    let value = (value + (1 << 13)) >> 14;
+
+    if quadrant < 2 {
+        value
+    } else {
+        -value
+    }
+}
+
+pub fn cos(angle: Angle) -> i32 {
+    sin(angle.wrapping_add(QUARTER_TURN))
+}
+
+pub fn from_radians(radians: f64) -> Angle {
+    /* Through i64 so negative angles and many turns wrap instead of saturating */
+    (radians * (4294967296.0 / core::f64::consts::TAU)) as i64 as Angle
+}
+
+pub fn to_f64(value: i32) -> f64 {
+    value as f64 / ONE as f64
+}
+
+/* Q16.16 number, wrapping like the integer it is (range ±32768) */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Fixed(pub i32);
+
+impl Fixed {
+    pub fn from_f64(value: f64) -> Self {
+        Fixed((value * ONE as f64) as i32)
+    }
+
+    pub fn to_f64(self) -> f64 {
+        to_f64(self.0)
+    }
+
+    /* Rounded towards zero, like `as i32` on a float */
+    pub fn to_i32(self) -> i32 {
+        self.0 / ONE
+    }
+
+    pub fn sin(angle: Angle) -> Self {
+        Fixed(sin(angle))
+    }
+
+    pub fn cos(angle: Angle) -> Self {
+        Fixed(cos(angle))
+    }
+}
+
+impl From<i32> for Fixed {
+    fn from(value: i32) -> Self {
+        Fixed(value.wrapping_mul(ONE))
+    }
+}
+
+impl Add for Fixed {
+    type Output = Fixed;
+
+    fn add(self, other: Fixed) -> Fixed {
+        Fixed(self.0.wrapping_add(other.0))
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Fixed;
+
+    fn sub(self, other: Fixed) -> Fixed {
+        Fixed(self.0.wrapping_sub(other.0))
+    }
+}
+
+impl Mul for Fixed {
+    type Output = Fixed;
+
+    fn mul(self, other: Fixed) -> Fixed {
+        Fixed(((self.0 as i64 * other.0 as i64) >> 16) as i32)
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Fixed;
+
+    fn neg(self) -> Fixed {
+        Fixed(self.0.wrapping_neg())
+    }
+}
+
+/* Same signature as `libm::sin`/`cos` */
+pub mod radians {
+    pub fn sin(radians: f64) -> f64 {
+        super::to_f64(super::sin(super::from_radians(radians)))
+    }
+
+    pub fn cos(radians: f64) -> f64 {
+        super::to_f64(super::cos(super::from_radians(radians)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* One Q16.16 step */
+    const MAX_ERROR: f64 = 1.0 / ONE as f64;
+
+    #[test]
+    fn matches_libm_over_full_turns() {
+        for i in -20_000..20_000 {
+            let x = i as f64 * 0.00731;
+            assert!((radians::sin(x) - libm::sin(x)).abs() < MAX_ERROR, "sin({})", x);
+            assert!((radians::cos(x) - libm::cos(x)).abs() < MAX_ERROR, "cos({})", x);
+        }
+    }
+
+    #[test]
+    fn large_angles_wrap() {
+        /* The gallery curves go up to a few hundred radians */
+        for x in [210.0, 465.3, -1000.25, 12345.678] {
+            assert!((radians::sin(x) - libm::sin(x)).abs() < MAX_ERROR, "sin({})", x);
+        }
+    }
+
+    #[test]
+    fn exact_at_quadrants() {
+        assert_eq!(sin(0), 0);
+        assert_eq!(sin(QUARTER_TURN), ONE);
+        assert_eq!(sin(2 * QUARTER_TURN), 0);
+        assert_eq!(sin(3 * QUARTER_TURN), -ONE);
+        assert_eq!(cos(0), ONE);
+    }
+
+    #[test]
+    fn fixed_arithmetic() {
+        let (a, b) = (Fixed::from_f64(-12.375), Fixed::from(3));
+        assert_eq!(a.to_f64(), -12.375);
+        assert_eq!((a + b).to_f64(), -9.375);
+        assert_eq!((a - b).to_f64(), -15.375);
+        assert_eq!((a * b).to_f64(), -37.125);
+        assert_eq!((-a).to_f64(), 12.375);
+        assert_eq!(a.to_i32(), -12);
+        assert_eq!(Fixed::from_f64(0.75).to_i32(), 0);
+    }
+
+    #[test]
+    fn fixed_polar_point() {
+        /* What a curve does per sample: radius times sine and cosine of a binary angle */
+        let radius = Fixed::from_f64(150.5);
+        for i in 0..1000u32 {
+            let angle = i.wrapping_mul(from_radians(0.0173));
+            let x = i as f64 * 0.0173;
+            assert!(((radius * Fixed::cos(angle)).to_f64() - 150.5 * libm::cos(x)).abs() < 0.01, "cos({})", x);
+            assert!(((radius * Fixed::sin(angle)).to_f64() - 150.5 * libm::sin(x)).abs() < 0.01, "sin({})", x);
+        }
+    }
+}
@@ -0,0 +1,290 @@
+/* Time zones from POSIX TZ strings, e.g. `CET-1CEST,M3.5.0,M10.5.0/3` for Central Europe.
+ *
+ * The string names the standard time and its offset, optionally followed by a daylight saving
+ * time with its offset (an hour ahead if left out) and the rules when it starts and ends (the
+ * US rules if left out). Offsets are POSIX style: hours *west* of UTC, so `CET-1` is UTC+1.
+ * Rules are `Mm.w.d` (day `d` of week `w` of month `m`, week 5 is the last one, day 0 is
+ * Sunday), `Jn` (day of the year 1..=365, February 29 not counted) or `n` (0..=365, counted),
+ * each optionally followed by `/time` of local time (02:00 if left out).
+ *
+ * Everything works on Unix timestamps (seconds), no allocation, so it runs on the host too. */
+
+#![no_std]
+
+mod parse;
+
+pub use parse::Error;
+
+/* Longest zone abbreviation kept, POSIX asks for at least 3 characters */
+pub const MAX_NAME: usize = 10;
+
+const HOUR: i32 = 3600;
+const DAY: i64 = 24 * 3600;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Zone {
+    name: [u8; MAX_NAME],
+    name_len: u8,
+    /* Seconds east of UTC (the opposite sign of the TZ string) */
+    pub offset: i32,
+}
+
+impl Zone {
+    pub fn name(&self) -> &str {
+        /* Only ASCII letters, digits and signs make it through the parser */
+        core::str::from_utf8(&self.name[..self.name_len as usize]).unwrap()
+    }
+}
+
+/* Day of the year a transition happens on */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Day {
+    /* 1..=365, February 29 is never counted */
+    Julian(u16),
+    /* 0..=365, February 29 counts in leap years */
+    Ordinal(u16),
+    /* `weekday` (0 = Sunday) of `week` 1..=5 of `month`, 5 means the last one */
+    Weekday { month: u8, week: u8, weekday: u8 },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Rule {
+    day: Day,
+    /* Local time of the transition in seconds after midnight, may be negative or past 24h */
+    time: i32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Dst {
+    zone: Zone,
+    /* Start in standard time, end in daylight saving time */
+    start: Rule,
+    end: Rule,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeZone {
+    std: Zone,
+    dst: Option<Dst>,
+}
+
+impl TimeZone {
+    pub fn parse(tz: &str) -> Result<Self, Error> {
+        parse::time_zone(tz)
+    }
+
+    /* Zone in effect at `utc` (Unix timestamp) */
+    pub fn zone_at(&self, utc: i64) -> &Zone {
+        if self.is_dst(utc) {
+            &self.dst.as_ref().unwrap().zone
+        } else {
+            &self.std
+        }
+    }
+
+    /* Seconds to add to `utc` to get the local time */
+    pub fn offset_at(&self, utc: i64) -> i32 {
+        self.zone_at(utc).offset
+    }
+
+    pub fn is_dst(&self, utc: i64) -> bool {
+        let Some(dst) = &self.dst else {
+            return false;
+        };
+
+        /* Rules are per calendar year, the year of the local standard time is close enough
+         * (transitions don't happen around new year) */
+        let year = year_of((utc + self.std.offset as i64).div_euclid(DAY));
+        let start = dst.start.instant(year) - self.std.offset as i64;
+        let end = dst.end.instant(year) - dst.zone.offset as i64;
+
+        if start < end {
+            start <= utc && utc < end
+        } else {
+            /* Southern hemisphere, daylight saving time spans new year */
+            !(end <= utc && utc < start)
+        }
+    }
+}
+
+impl Rule {
+    /* Local time of the transition in `year`, as seconds since 1970-01-01 00:00 */
+    fn instant(&self, year: i64) -> i64 {
+        let day = match self.day {
+            Day::Julian(n) => {
+                let skip_leap_day = is_leap(year) && n >= 60;
+                days_from_civil(year, 1, 1) + n as i64 - 1 + skip_leap_day as i64
+            }
+            Day::Ordinal(n) => days_from_civil(year, 1, 1) + n as i64,
+            Day::Weekday { month, week, weekday } => {
+                let first = days_from_civil(year, month as u32, 1);
+                let first_weekday = (first + 4).rem_euclid(7); /* 1970-01-01 was a Thursday */
+                let mut day = first + (weekday as i64 - first_weekday).rem_euclid(7) + 7 * (week as i64 - 1);
+                let next_month = if month == 12 {
+                    days_from_civil(year + 1, 1, 1)
+                } else {
+                    days_from_civil(year, month as u32 + 1, 1)
+                };
+                while day >= next_month {
+                    day -= 7;
+                }
+                day
+            }
+        };
+        day * DAY + self.time as i64
+    }
+}
+
+fn is_leap(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+/* Days since 1970-01-01 of a proleptic Gregorian date (Howard Hinnant's algorithm) */
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn year_of(days: i64) -> i64 {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    /* Counted from March, January and February belong to the next year */
+    year_of_era + era * 400 + (month_index >= 10) as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CET: &str = "CET-1CEST,M3.5.0,M10.5.0/3";
+
+    /* Unix timestamp of a UTC date and time */
+    fn utc(year: i64, month: u32, day: u32, hour: i64, minute: i64) -> i64 {
+        days_from_civil(year, month, day) * DAY + hour * 3600 + minute * 60
+    }
+
+    #[test]
+    fn calendar() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11017);
+        assert_eq!(year_of(days_from_civil(2023, 12, 31)), 2023);
+        assert_eq!(year_of(days_from_civil(2024, 1, 1)), 2024);
+        assert_eq!(year_of(days_from_civil(1969, 12, 31)), 1969);
+    }
+
+    #[test]
+    fn parses_zone_names_and_offsets() {
+        let tz = TimeZone::parse(CET).unwrap();
+        assert_eq!(tz.std.name(), "CET");
+        assert_eq!(tz.std.offset, HOUR);
+        let dst = tz.dst.unwrap();
+        assert_eq!(dst.zone.name(), "CEST");
+        assert_eq!(dst.zone.offset, 2 * HOUR);
+        assert_eq!(dst.end, Rule { day: Day::Weekday { month: 10, week: 5, weekday: 0 }, time: 3 * HOUR });
+
+        let tz = TimeZone::parse("<+0530>-5:30").unwrap();
+        assert_eq!(tz.std.name(), "+0530");
+        assert_eq!(tz.std.offset, 5 * HOUR + 30 * 60);
+        assert!(tz.dst.is_none());
+
+        let tz = TimeZone::parse("UTC0").unwrap();
+        assert_eq!(tz.offset_at(1_700_000_000), 0);
+    }
+
+    #[test]
+    fn rejects_malformed_strings() {
+        assert_eq!(TimeZone::parse(""), Err(Error::Name));
+        assert_eq!(TimeZone::parse("CE-1"), Err(Error::Name));
+        assert_eq!(TimeZone::parse("CET"), Err(Error::Offset));
+        assert_eq!(TimeZone::parse("CET-1CEST,M3.5"), Err(Error::Rule));
+        assert_eq!(TimeZone::parse("CET-1CEST,M13.5.0,M10.5.0"), Err(Error::Rule));
+        assert_eq!(TimeZone::parse("CET-1CEST,M3.5.0"), Err(Error::Rule));
+        assert_eq!(TimeZone::parse("CET-1CEST,M3.5.0,M10.5.0/3 "), Err(Error::Trailing));
+        assert_eq!(TimeZone::parse("CET-1 "), Err(Error::Name));
+    }
+
+    #[test]
+    fn central_europe_transitions() {
+        let tz = TimeZone::parse(CET).unwrap();
+
+        /* 2023: summer time from March 26 01:00 UTC to October 29 01:00 UTC */
+        let start = utc(2023, 3, 26, 1, 0);
+        assert_eq!(tz.offset_at(start - 1), HOUR);
+        assert_eq!(tz.zone_at(start - 1).name(), "CET");
+        assert_eq!(tz.offset_at(start), 2 * HOUR);
+        assert_eq!(tz.zone_at(start).name(), "CEST");
+
+        let end = utc(2023, 10, 29, 1, 0);
+        assert_eq!(tz.offset_at(end - 1), 2 * HOUR);
+        assert_eq!(tz.offset_at(end), HOUR);
+
+        /* 2024: last Sundays are March 31 and October 27 */
+        assert!(!tz.is_dst(utc(2024, 3, 31, 0, 59)));
+        assert!(tz.is_dst(utc(2024, 3, 31, 1, 0)));
+        assert!(tz.is_dst(utc(2024, 10, 27, 0, 59)));
+        assert!(!tz.is_dst(utc(2024, 10, 27, 1, 0)));
+
+        assert!(!tz.is_dst(utc(2024, 1, 15, 12, 0)));
+        assert!(tz.is_dst(utc(2024, 7, 15, 12, 0)));
+    }
+
+    #[test]
+    fn us_default_rules() {
+        /* No rules given: second Sunday of March to first Sunday of November at 02:00 */
+        let tz = TimeZone::parse("EST5EDT").unwrap();
+        let start = utc(2023, 3, 12, 7, 0);
+        assert_eq!(tz.offset_at(start - 1), -5 * HOUR);
+        assert_eq!(tz.offset_at(start), -4 * HOUR);
+        let end = utc(2023, 11, 5, 6, 0);
+        assert_eq!(tz.offset_at(end - 1), -4 * HOUR);
+        assert_eq!(tz.offset_at(end), -5 * HOUR);
+    }
+
+    #[test]
+    fn southern_hemisphere_spans_new_year() {
+        let tz = TimeZone::parse("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+
+        /* Summer time ends April 2 2023 03:00 AEDT (16:00 UTC the day before) */
+        let end = utc(2023, 4, 1, 16, 0);
+        assert_eq!(tz.offset_at(end - 1), 11 * HOUR);
+        assert_eq!(tz.offset_at(end), 10 * HOUR);
+
+        /* and starts October 1 2023 02:00 AEST (16:00 UTC the day before) */
+        let start = utc(2023, 9, 30, 16, 0);
+        assert_eq!(tz.offset_at(start - 1), 10 * HOUR);
+        assert_eq!(tz.offset_at(start), 11 * HOUR);
+
+        assert!(tz.is_dst(utc(2023, 12, 31, 23, 0)));
+        assert!(tz.is_dst(utc(2024, 1, 1, 1, 0)));
+    }
+
+    #[test]
+    fn julian_and_ordinal_days() {
+        /* Day 60 is March 1 with `J` in every year, with a plain number only in leap years */
+        let julian = TimeZone::parse("AAA0BBB,J60/0,J300/0").unwrap();
+        assert!(julian.is_dst(utc(2024, 3, 1, 0, 0)));
+        assert!(!julian.is_dst(utc(2024, 2, 29, 23, 59)));
+
+        let ordinal = TimeZone::parse("AAA0BBB,59/0,300/0").unwrap();
+        assert!(ordinal.is_dst(utc(2024, 2, 29, 0, 0)));
+        assert!(!ordinal.is_dst(utc(2024, 2, 28, 23, 59)));
+        assert!(ordinal.is_dst(utc(2023, 3, 1, 0, 0)));
+    }
+
+    #[test]
+    fn explicit_dst_offset_and_times() {
+        /* Half an hour of summer time, switching at 02:30 */
+        let tz = TimeZone::parse("LHST-10:30LHDT-11,M10.1.0/2:30,M4.1.0/2").unwrap();
+        assert_eq!(tz.dst.unwrap().zone.offset, 11 * HOUR);
+        assert_eq!(tz.dst.unwrap().start.time, 2 * HOUR + 30 * 60);
+    }
+}
@@ -0,0 +1,172 @@
+/* TZ string parser, see the crate docs for the format */
+
+use crate::{Day, Dst, Rule, TimeZone, Zone, HOUR, MAX_NAME};
+
+/* Part of the TZ string that couldn't be parsed */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /* Missing, shorter than 3 or longer than `MAX_NAME` characters */
+    Name,
+    Offset,
+    /* Transition rule, or only one of the two given */
+    Rule,
+    /* Something left after the end rule */
+    Trailing,
+}
+
+/* Used when a daylight saving time has no rules (the US ones, like glibc does) */
+const DEFAULT_START: Rule = Rule { day: Day::Weekday { month: 3, week: 2, weekday: 0 }, time: 2 * HOUR };
+const DEFAULT_END: Rule = Rule { day: Day::Weekday { month: 11, week: 1, weekday: 0 }, time: 2 * HOUR };
+
+/* Transitions happen at 02:00 local time unless the rule says otherwise */
+const DEFAULT_TIME: i32 = 2 * HOUR;
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+pub(crate) fn time_zone(tz: &str) -> Result<TimeZone, Error> {
+    let mut parser = Parser { bytes: tz.as_bytes(), position: 0 };
+
+    let std = parser.zone()?;
+    if parser.is_done() {
+        return Ok(TimeZone { std, dst: None });
+    }
+
+    let mut dst_zone = Zone { offset: std.offset + HOUR, ..parser.name()? };
+    if !parser.is_done() && parser.peek() != Some(b',') {
+        dst_zone.offset = -parser.time(24)?;
+    }
+
+    let (start, end) = if parser.eat(b',') {
+        let start = parser.rule()?;
+        if !parser.eat(b',') {
+            return Err(Error::Rule);
+        }
+        (start, parser.rule()?)
+    } else {
+        (DEFAULT_START, DEFAULT_END)
+    };
+
+    if !parser.is_done() {
+        return Err(Error::Trailing);
+    }
+    Ok(TimeZone { std, dst: Some(Dst { zone: dst_zone, start, end }) })
+}
+
+impl Parser<'_> {
+    fn is_done(&self) -> bool {
+        self.position == self.bytes.len()
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.position).copied()
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        let found = self.peek() == Some(byte);
+        self.position += found as usize;
+        found
+    }
+
+    /* Name followed by its offset, the standard time always has one */
+    fn zone(&mut self) -> Result<Zone, Error> {
+        let zone = self.name()?;
+        if self.is_done() {
+            return Err(Error::Offset);
+        }
+        Ok(Zone { offset: -self.time(24)?, ..zone })
+    }
+
+    /* Letters only, or anything alphanumeric (and signs) between `<` and `>` */
+    fn name(&mut self) -> Result<Zone, Error> {
+        let quoted = self.eat(b'<');
+        let start = self.position;
+        while let Some(byte) = self.peek() {
+            let allowed = byte.is_ascii_alphabetic()
+                || quoted && (byte.is_ascii_digit() || byte == b'+' || byte == b'-');
+            if !allowed {
+                break;
+            }
+            self.position += 1;
+        }
+        let name = &self.bytes[start..self.position];
+        if quoted && !self.eat(b'>') || name.len() < 3 || name.len() > MAX_NAME {
+            return Err(Error::Name);
+        }
+
+        let mut zone = Zone { name: [0; MAX_NAME], name_len: name.len() as u8, offset: 0 };
+        zone.name[..name.len()].copy_from_slice(name);
+        Ok(zone)
+    }
+
+    /* [+-]hh[:mm[:ss]] in seconds, hours up to `max_hours` */
+    fn time(&mut self, max_hours: u32) -> Result<i32, Error> {
+        let negative = self.eat(b'-');
+        if !negative {
+            self.eat(b'+');
+        }
+
+        let hours = self.number(Error::Offset)?;
+        if hours > max_hours {
+            return Err(Error::Offset);
+        }
+        let mut seconds = hours as i32 * HOUR;
+        for unit in [60, 1] {
+            if !self.eat(b':') {
+                break;
+            }
+            let value = self.number(Error::Offset)?;
+            if value > 59 {
+                return Err(Error::Offset);
+            }
+            seconds += value as i32 * unit;
+        }
+        Ok(if negative { -seconds } else { seconds })
+    }
+
+    fn number(&mut self, error: Error) -> Result<u32, Error> {
+        let start = self.position;
+        let mut value: u32 = 0;
+        while let Some(digit @ b'0'..=b'9') = self.peek() {
+            value = value.saturating_mul(10).saturating_add((digit - b'0') as u32);
+            self.position += 1;
+        }
+        if self.position == start {
+            Err(error)
+        } else {
+            Ok(value)
+        }
+    }
+
+    /* Mm.w.d, Jn or n, then an optional /time (up to 167 hours either way) */
+    fn rule(&mut self) -> Result<Rule, Error> {
+        let day = if self.eat(b'M') {
+            let month = self.number(Error::Rule)?;
+            let week = self.eat(b'.').then(|| self.number(Error::Rule)).ok_or(Error::Rule)??;
+            let weekday = self.eat(b'.').then(|| self.number(Error::Rule)).ok_or(Error::Rule)??;
+            if !(1..=12).contains(&month) || !(1..=5).contains(&week) || weekday > 6 {
+                return Err(Error::Rule);
+            }
+            Day::Weekday { month: month as u8, week: week as u8, weekday: weekday as u8 }
+        } else if self.eat(b'J') {
+            match self.number(Error::Rule)? {
+                day @ 1..=365 => Day::Julian(day as u16),
+                _ => return Err(Error::Rule),
+            }
+        } else {
+            match self.number(Error::Rule)? {
+                day @ 0..=365 => Day::Ordinal(day as u16),
+                _ => return Err(Error::Rule),
+            }
+        };
+
+        let time = if self.eat(b'/') {
+            self.time(167).map_err(|_| Error::Rule)?
+        } else {
+            DEFAULT_TIME
+        };
+        Ok(Rule { day, time })
+    }
+}